@@ -2,7 +2,7 @@ use clap::{Parser, Subcommand};
 use std::{path::PathBuf, str};
 use viewd::{
     clients::{Client, Config},
-    window::DISPLAY_PATH,
+    window::{Listing, DISPLAY_PATH},
 };
 
 #[derive(Parser, Debug)]
@@ -23,13 +23,91 @@ struct Cli {
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    /// Get the value of key.
-    Get,
+    /// Get the value of key, by default the image displayed.
+    Get {
+        #[clap(default_value = DISPLAY_PATH)]
+        key: String,
+    },
     Rotate,
     Fullscreen,
-    Pageant,
+    /// Toggle pageant mode, or change it with any of `start`, `stop`,
+    /// `toggle`, an interval in milliseconds, `forward`, `reverse`,
+    /// `sequential` and `shuffle`
+    Pageant {
+        settings: Vec<String>,
+    },
     Next,
     Prev,
+    /// Set how images are scaled: fit, fill, actual or fit-width
+    Scale {
+        mode: String,
+    },
+    /// Zoom the image: in, out or reset
+    Zoom {
+        action: String,
+    },
+    /// Move a zoomed image: up, down, left, right or a point `x,y` with
+    /// coordinates between 0 and 1
+    Pan {
+        direction: String,
+    },
+    /// Play an animated image: pause, resume, toggle, or step to the next
+    /// or prev frame
+    Animation {
+        #[clap(default_value = "toggle")]
+        action: String,
+    },
+    /// Jump to an image by index, offset (+N/-N), relative path or file
+    /// name and print where the cursor landed
+    Goto {
+        #[clap(allow_hyphen_values = true)]
+        target: String,
+    },
+    /// Describe the state of the server
+    Info {
+        /// Print JSON instead of a table
+        #[clap(long)]
+        json: bool,
+    },
+    /// Rescan the image directory on the server, or with `config` make it
+    /// re-read its config file and certificates
+    Reload {
+        #[arg(value_parser = ["config"])]
+        target: Option<String>,
+    },
+    /// Change which files are shown with `name=value` settings: ext,
+    /// include, exclude, hidden, max-depth, follow-symlinks, min-size and
+    /// max-size. `reset` restores the server config
+    Filter {
+        settings: Vec<String>,
+    },
+    /// Order images by name, path, natural, modified, size, taken (EXIF
+    /// date) or shuffle, optionally seeded as in `shuffle:42`
+    Sort {
+        order: String,
+    },
+    /// List the images on the server
+    List {
+        #[clap(long, default_value_t = 0)]
+        offset: usize,
+        #[clap(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Find images by glob (`*.png`, `2023/**`) or substring
+    Search {
+        pattern: String,
+        #[clap(long, default_value_t = 0)]
+        offset: usize,
+        #[clap(long, default_value_t = 100)]
+        limit: usize,
+    },
+    /// Send an image to the server
+    Upload {
+        file: PathBuf,
+        /// Display the image once uploaded
+        #[clap(long)]
+        show: bool,
+    },
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -60,9 +138,8 @@ async fn main() -> viewd::Result<()> {
             client.set("prev", vec![].into()).await?;
             println!("OK");
         }
-        Command::Get => {
-            let s = DISPLAY_PATH;
-            if let Some(value) = client.get(s).await? {
+        Command::Get { key } => {
+            if let Some(value) = client.get(&key).await? {
                 if let Ok(string) = str::from_utf8(&value) {
                     println!("\"{}\"", string);
                 } else {
@@ -80,11 +157,84 @@ async fn main() -> viewd::Result<()> {
             client.set("fullscreen", vec![].into()).await?;
             println!("OK");
         }
-        Command::Pageant => {
-            client.set("pageant", vec![].into()).await?;
+        Command::Pageant { settings } => {
+            client.set("pageant", settings.join(" ").into()).await?;
+            println!("OK");
+        }
+        Command::Scale { mode } => {
+            client.set("scale", mode.into()).await?;
+            println!("OK");
+        }
+        Command::Zoom { action } => {
+            client.set("zoom", action.into()).await?;
+            println!("OK");
+        }
+        Command::Pan { direction } => {
+            client.set("pan", direction.into()).await?;
+            println!("OK");
+        }
+        Command::Animation { action } => {
+            client.set("animation", action.into()).await?;
+            println!("OK");
+        }
+        Command::Goto { target } => {
+            let position = client.goto(&target).await?;
+            println!("{}", position);
+        }
+        Command::Info { json } => {
+            let info = client.info().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+            } else {
+                for (name, value) in info.fields() {
+                    // line up values spanning several lines
+                    let value = value.replace('\n', &format!("\n{:14}", ""));
+                    println!("{:<14}{}", name, value);
+                }
+            }
+        }
+        Command::Sort { order } => {
+            let position = client.sort(&order).await?;
+            println!("{}", position);
+        }
+        Command::Filter { settings } => {
+            let position = client.filter(settings).await?;
+            println!("{}", position);
+        }
+        Command::Reload { target: None } => {
+            let position = client.reload().await?;
+            println!("{}", position);
+        }
+        Command::Reload { target: Some(_) } => {
+            client.reload_config().await?;
+            println!("OK");
+        }
+        Command::List { offset, limit } => {
+            let listing = client.list(offset, limit).await?;
+            print_listing(&listing);
+        }
+        Command::Search {
+            pattern,
+            offset,
+            limit,
+        } => {
+            let listing = client.search(&pattern, offset, limit).await?;
+            print_listing(&listing);
+        }
+        Command::Upload { file, show } => {
+            client.upload(file.as_path(), show).await?;
             println!("OK");
         }
     }
 
     Ok(())
 }
+
+/// Print one entry per line, marking the image currently displayed
+fn print_listing(listing: &Listing) {
+    for (index, path) in &listing.entries {
+        let marker = if *index == listing.current { '*' } else { ' ' };
+        println!("{} {:>6} {}", marker, index, path.display());
+    }
+    println!("({} of {})", listing.entries.len(), listing.total);
+}
@@ -9,7 +9,8 @@
 use clap::Parser;
 use std::path::PathBuf;
 use viewd::control;
-use viewd::serve::Config;
+use viewd::sdl_window::{Headless, SdlWindow};
+use viewd::serve::{bind_addrs, Config};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -23,19 +24,43 @@ async fn main() -> anyhow::Result<()> {
         .path
         .unwrap_or_else(|| config.path.as_path().to_path_buf());
 
-    control::run(&format!("127.0.0.1:{}", port), &path, config).await?;
+    let bind = if cli.bind.is_empty() {
+        config.bind.clone()
+    } else {
+        cli.bind
+    };
+    let addrs = bind_addrs(&bind, port)?;
+
+    if cli.headless {
+        let display = Headless::new(HEADLESS_SIZE);
+        control::run(&addrs, &path, &cli.config, config, display).await?;
+    } else {
+        let display = SdlWindow::new("viewd")?;
+        control::run(&addrs, &path, &cli.config, config, display).await?;
+    }
     Ok(())
 }
 
+/// Window size images are laid out in without a display
+const HEADLESS_SIZE: (u32, u32) = (1920, 1080);
+
 #[derive(Parser, Debug)]
 #[clap(name = "viewd-server", version, author, about = "A Viewd Server")]
 pub struct Cli {
     #[clap(long)]
     port: Option<u16>,
+    /// Address to listen on, may be given more than once. Overrides `bind`
+    /// from the config file.
+    #[clap(long)]
+    bind: Vec<String>,
     #[clap(long)]
     path: Option<PathBuf>,
     #[clap(long, short, default_value = "config/server/example.toml")]
     config: PathBuf,
+    /// Run without opening a window, e.g. on a machine without a display.
+    /// Clients can use the server as usual.
+    #[clap(long)]
+    headless: bool,
 }
 
 fn set_up_logging() -> anyhow::Result<()> {
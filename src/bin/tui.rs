@@ -1,7 +1,7 @@
 use clap::Parser;
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use std::path::PathBuf;
 use tracing::debug;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use viewd::{
     clients::{Client, Config},
     tui::Tui,
@@ -30,12 +30,16 @@ async fn main() -> viewd::Result<()> {
     let host = cli.host.unwrap_or(config.host.to_string());
     let port = cli.port.unwrap_or(config.port);
 
-    // Establish a connection
-    let client = Client::connect(&host, port, con_config).await?;
+    // Establish a connection for commands and one for state updates
+    let client = Client::connect(&host, port, con_config.clone()).await?;
+    let subscriber = Client::connect(&host, port, con_config)
+        .await?
+        .subscribe(vec![])
+        .await?;
 
     enable_raw_mode()?;
 
-    let mut tui = Tui::new(client);
+    let mut tui = Tui::new(client, subscriber);
     tui.set_title()?;
 
     if let Err(e) = tui.handle_events().await {
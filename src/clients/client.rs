@@ -1,10 +1,19 @@
-use crate::cmd::{Get, Set};
+use crate::cmd::{
+    goto::position_from_frame, info::info_from_frame, list::listing_from_frame, Filter, Get, Goto,
+    Info, List, Reload, Search, Set, Sort, Subscribe, Upload,
+};
+use crate::frame::MAX_CHUNK_SIZE;
+use crate::window::{Listing, Position, Status};
 use crate::{Connection, Frame};
 
 use crate::clients::{config::Config, tls::connector};
 use bytes::Bytes;
+use futures::Stream;
 use std::io::{Error, ErrorKind};
+use std::path::Path;
 use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
 use tokio::net::TcpStream;
 use tokio_rustls::rustls::ServerName;
 
@@ -14,6 +23,21 @@ pub struct Client {
     connection: Connection,
 }
 
+/// A client that has entered subscribe mode.
+///
+/// Once a client subscribes, it can no longer issue commands. The server
+/// pushes a [`Message`] every time a subscribed key changes.
+pub struct Subscriber {
+    client: Client,
+}
+
+/// A key change pushed by the server to a [`Subscriber`].
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub key: String,
+    pub value: Bytes,
+}
+
 impl Client {
     pub async fn connect(host: &str, port: u16, config: Arc<Config>) -> crate::Result<Client> {
         // Get the remote address to connect to
@@ -81,6 +105,189 @@ impl Client {
         }
     }
 
+    /// Jump to `target` and return the position the server landed on.
+    ///
+    /// See [`Target`](crate::window::Target) for the accepted forms.
+    #[instrument(skip(self))]
+    pub async fn goto(&mut self, target: &str) -> crate::Result<Position> {
+        let frame = Goto::new(target).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        position_from_frame(response)
+    }
+
+    /// Describe the state of the server and the image it displays.
+    #[instrument(skip(self))]
+    pub async fn info(&mut self) -> crate::Result<Status> {
+        let frame = Info::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        info_from_frame(response)
+    }
+
+    /// Change the order of the collection and return the position of the
+    /// current image in it.
+    #[instrument(skip(self))]
+    pub async fn sort(&mut self, order: &str) -> crate::Result<Position> {
+        let frame = Sort::new(order).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        position_from_frame(response)
+    }
+
+    /// Change which files are part of the collection, see
+    /// [`Filter`](crate::cmd::Filter), and return the position of the
+    /// current image after the rescan.
+    #[instrument(skip(self))]
+    pub async fn filter(&mut self, settings: Vec<String>) -> crate::Result<Position> {
+        let frame = Filter::new(settings).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        position_from_frame(response)
+    }
+
+    /// Rescan the image directory on the server and return the position of
+    /// the current image in the new collection.
+    #[instrument(skip(self))]
+    pub async fn reload(&mut self) -> crate::Result<Position> {
+        let frame = Reload::new().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        position_from_frame(response)
+    }
+
+    /// Make the server re-read its config file and certificates. Fails
+    /// with the reason if the server rejected the new config.
+    #[instrument(skip(self))]
+    pub async fn reload_config(&mut self) -> crate::Result<()> {
+        let frame = Reload::config().into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
+    /// List up to `limit` images of the collection, skipping the first
+    /// `offset`.
+    #[instrument(skip(self))]
+    pub async fn list(&mut self, offset: usize, limit: usize) -> crate::Result<Listing> {
+        let frame = List::new(offset, limit).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        listing_from_frame(response)
+    }
+
+    /// Find images matching the glob or substring `pattern`, returning up
+    /// to `limit` matches after skipping the first `offset`.
+    #[instrument(skip(self))]
+    pub async fn search(
+        &mut self,
+        pattern: &str,
+        offset: usize,
+        limit: usize,
+    ) -> crate::Result<Listing> {
+        let frame = Search::new(pattern, offset, limit).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        let response = self.read_response().await?;
+        listing_from_frame(response)
+    }
+
+    /// Subscribe to changes of `keys`, or to all keys if `keys` is
+    /// empty.
+    ///
+    /// The client is consumed as the connection can only receive
+    /// messages from now on. The current values of the keys are
+    /// delivered first.
+    #[instrument(skip(self))]
+    pub async fn subscribe(mut self, keys: Vec<String>) -> crate::Result<Subscriber> {
+        let frame = Subscribe::new(keys).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        Ok(Subscriber { client: self })
+    }
+
+    /// Upload the image at `path` to the server, displaying it once
+    /// complete if `show` is set.
+    ///
+    /// The file is streamed in `DataChunk` frames, so it is never held in
+    /// memory as a whole.
+    #[instrument(skip(self))]
+    pub async fn upload(&mut self, path: &Path, show: bool) -> crate::Result<()> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("invalid file name: {}", path.display()))?;
+        let mut file = File::open(path).await?;
+        let size = file.metadata().await?.len();
+
+        let frame = Upload::new(name, size, show).into_frame();
+
+        debug!(request = ?frame);
+
+        self.connection.write_frame(&frame).await?;
+
+        // The server acknowledges the announcement before any data is sent.
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => {}
+            frame => return Err(frame.to_error()),
+        }
+
+        let mut buf = vec![0; MAX_CHUNK_SIZE];
+        let mut sent = 0;
+        while sent < size {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                return Err("file truncated during upload".into());
+            }
+            let chunk = Bytes::copy_from_slice(&buf[..n]);
+            self.connection
+                .write_frame(&Frame::DataChunk { chunk })
+                .await?;
+            sent += n as u64;
+        }
+
+        match self.read_response().await? {
+            Frame::Simple(response) if response == "OK" => Ok(()),
+            frame => Err(frame.to_error()),
+        }
+    }
+
     /// Reads a response frame from the socket.
     ///
     /// If an `Error` frame is received, it is converted to `Err`.
@@ -104,3 +311,44 @@ impl Client {
         }
     }
 }
+
+impl Subscriber {
+    /// Receive the next message pushed by the server, waiting if
+    /// necessary.
+    ///
+    /// `None` indicates the server closed the subscription.
+    pub async fn next_message(&mut self) -> crate::Result<Option<Message>> {
+        match self.client.connection.read_frame().await? {
+            Some(Frame::Array(ref frame)) => match frame.as_slice() {
+                [Frame::Bulk(kind), Frame::Bulk(key), Frame::Bulk(value)]
+                    if &kind[..] == b"message" =>
+                {
+                    Ok(Some(Message {
+                        key: String::from_utf8(key.to_vec())?,
+                        value: value.clone(),
+                    }))
+                }
+                _ => Err(Frame::Array(frame.clone()).to_error()),
+            },
+            Some(Frame::Error(msg)) => Err(msg.into()),
+            Some(frame) => Err(frame.to_error()),
+            None => Ok(None),
+        }
+    }
+
+    /// Convert the subscriber into a `Stream` of messages.
+    pub fn into_stream(self) -> impl Stream<Item = crate::Result<Message>> {
+        futures::stream::unfold(self, |mut subscriber| async move {
+            subscriber
+                .next_message()
+                .await
+                .transpose()
+                .map(|message| (message, subscriber))
+        })
+    }
+
+    /// Send the Tls Stream the shutdown signal
+    pub async fn shutdown(self) -> crate::Result<()> {
+        self.client.shutdown().await
+    }
+}
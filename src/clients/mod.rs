@@ -4,4 +4,4 @@ mod config;
 pub use config::Config;
 
 mod client;
-pub use client::Client;
+pub use client::{Client, Message, Subscriber};
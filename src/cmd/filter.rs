@@ -0,0 +1,83 @@
+use crate::cmd::goto::position_to_frame;
+use crate::parse::ParseError;
+use crate::window::WindowCommand;
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{debug, instrument};
+
+/// Change which files are part of the collection and rescan it.
+///
+/// Each setting is a `name=value` pair such as `ext=jpg,png`,
+/// `exclude=**/thumbs/**`, `hidden=off`, `max-depth=2`,
+/// `follow-symlinks=on` or `min-size=10k`, applied on top of the current
+/// filter. `reset` restores the filter of the server config. The server
+/// replies with the position of the current image after the rescan. The
+/// current filter can be read with `GET filter`.
+#[derive(Debug)]
+pub struct Filter {
+    settings: Vec<String>,
+}
+
+impl Filter {
+    /// Create a new `Filter` command applying `settings`.
+    pub fn new(settings: Vec<String>) -> Filter {
+        Filter { settings }
+    }
+
+    /// Parse a `Filter` instance from a received frame.
+    ///
+    /// The `FILTER` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// FILTER [setting ...]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Filter> {
+        let mut settings = vec![];
+        loop {
+            match parse.next_string() {
+                Ok(setting) => settings.push(setting),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Filter { settings })
+    }
+
+    /// Transmit the settings to the `SdlWindow` instance and write the
+    /// resulting position to `dst`.
+    #[instrument(skip(self, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(WindowCommand::Filter(self.settings, reply_tx))
+            .await?;
+
+        let response = match reply_rx.await? {
+            Ok(position) => position_to_frame(&position),
+            Err(e) => Frame::Error(format!("ERR {}", e)),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("filter".as_bytes()));
+        for setting in self.settings {
+            frame.push_bulk(Bytes::from(setting.into_bytes()));
+        }
+        frame
+    }
+}
@@ -0,0 +1,101 @@
+use crate::window::{Position, Target, WindowCommand};
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use std::ffi::OsString;
+use std::os::unix::prelude::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{debug, instrument};
+
+/// Jump to an image.
+///
+/// The target is an absolute index, a relative offset such as `+10` or
+/// `-5`, a path relative to the image directory or a file name. The
+/// server replies with the position the cursor landed on.
+#[derive(Debug)]
+pub struct Goto {
+    target: String,
+}
+
+impl Goto {
+    /// Create a new `Goto` command jumping to `target`.
+    pub fn new(target: impl ToString) -> Goto {
+        Goto {
+            target: target.to_string(),
+        }
+    }
+
+    /// Parse a `Goto` instance from a received frame.
+    ///
+    /// The `GOTO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// GOTO target
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Goto> {
+        let target = parse.next_string()?;
+        Ok(Goto { target })
+    }
+
+    /// Transmit the `Goto` command to the `SdlWindow` instance and wait for
+    /// the resulting position.
+    #[instrument(skip(self, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let target: Target = self.target.parse()?;
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(WindowCommand::Goto(target, reply_tx)).await?;
+
+        let response = match reply_rx.await? {
+            Ok(position) => position_to_frame(&position),
+            Err(e) => Frame::Error(format!("ERR {}", e)),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Goto` command to send
+    /// to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("goto".as_bytes()));
+        frame.push_bulk(Bytes::from(self.target.into_bytes()));
+        frame
+    }
+}
+
+/// Encode a position as `[index, total, path]`
+pub(crate) fn position_to_frame(position: &Position) -> Frame {
+    Frame::Array(vec![
+        Frame::Integer(position.index as u64),
+        Frame::Integer(position.total as u64),
+        Frame::Bulk(Bytes::copy_from_slice(position.path.as_os_str().as_bytes())),
+    ])
+}
+
+/// Decode a position encoded by [`position_to_frame`]
+pub(crate) fn position_from_frame(frame: Frame) -> crate::Result<Position> {
+    match frame {
+        Frame::Array(parts) => match parts.as_slice() {
+            [Frame::Integer(index), Frame::Integer(total), Frame::Bulk(path)] => Ok(Position {
+                index: *index as usize,
+                total: *total as usize,
+                path: PathBuf::from(OsString::from_vec(path.to_vec())),
+            }),
+            _ => Err(Frame::Array(parts).to_error()),
+        },
+        frame => Err(frame.to_error()),
+    }
+}
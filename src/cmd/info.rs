@@ -0,0 +1,95 @@
+use crate::window::{Status, WindowCommand};
+use crate::{Connection, Db, Frame, Parse};
+
+use bytes::Bytes;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{debug, instrument};
+
+/// Describe the state of the server.
+///
+/// The reply is a flat array of field names and values: the position,
+/// path, dimensions and file size of the current image, the window
+/// settings, pageant mode, uptime and the number of connected clients.
+#[derive(Debug, Default)]
+pub struct Info;
+
+impl Info {
+    /// Create a new `Info` command.
+    pub fn new() -> Info {
+        Info
+    }
+
+    /// Parse an `Info` instance from a received frame.
+    ///
+    /// The `INFO` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// INFO
+    /// ```
+    pub(crate) fn parse_frames(_parse: &mut Parse) -> crate::Result<Info> {
+        Ok(Info)
+    }
+
+    /// Ask the `SdlWindow` instance for its state, add the server wide
+    /// fields and write the result to `dst`.
+    #[instrument(skip(self, db, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(WindowCommand::Info(reply_tx)).await?;
+        let mut info = reply_rx.await?;
+        info.uptime = db.uptime().as_secs();
+        info.clients = db.clients();
+
+        let response = info_to_frame(&info);
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("info".as_bytes()));
+        frame
+    }
+}
+
+/// Encode the state as `[name, value, name, value, ...]`
+pub(crate) fn info_to_frame(info: &Status) -> Frame {
+    let mut frame = Frame::array();
+    for (name, value) in info.fields() {
+        frame.push_bulk(Bytes::from(name.as_bytes()));
+        frame.push_bulk(Bytes::from(value.into_bytes()));
+    }
+    frame
+}
+
+/// Decode a state encoded by [`info_to_frame`]
+pub(crate) fn info_from_frame(frame: Frame) -> crate::Result<Status> {
+    let parts = match frame {
+        Frame::Array(parts) if parts.len() % 2 == 0 => parts,
+        frame => return Err(frame.to_error()),
+    };
+    let mut info = Status::default();
+    for field in parts.chunks(2) {
+        match field {
+            [Frame::Bulk(name), Frame::Bulk(value)] => {
+                let name = std::str::from_utf8(name)?;
+                let value = std::str::from_utf8(value)?;
+                info.set_field(name, value)?;
+            }
+            _ => return Err("protocol error; invalid info field".into()),
+        }
+    }
+    Ok(info)
+}
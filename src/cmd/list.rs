@@ -0,0 +1,210 @@
+use crate::parse::{Parse, ParseError};
+use crate::window::{Listing, Query, WindowCommand};
+use crate::{Connection, Frame};
+
+use bytes::Bytes;
+use std::ffi::OsString;
+use std::os::unix::prelude::{OsStrExt, OsStringExt};
+use std::path::PathBuf;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{debug, instrument};
+
+/// Number of entries returned when no limit is given
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// List the images of the collection.
+///
+/// Replies with the index of the current image, the size of the
+/// collection and a page of indices and paths relative to the image
+/// directory.
+#[derive(Debug)]
+pub struct List {
+    offset: usize,
+    limit: usize,
+}
+
+/// Find images whose relative path matches a pattern.
+///
+/// Patterns containing `*`, `?` or `[` are globs, anything else is a case
+/// insensitive substring. The reply has the same format as `LIST`, with
+/// the total being the number of matches.
+#[derive(Debug)]
+pub struct Search {
+    pattern: String,
+    offset: usize,
+    limit: usize,
+}
+
+impl List {
+    /// Create a new `List` command returning up to `limit` entries after
+    /// skipping `offset`.
+    pub fn new(offset: usize, limit: usize) -> List {
+        List { offset, limit }
+    }
+
+    /// Parse a `List` instance from a received frame.
+    ///
+    /// The `LIST` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// LIST [offset [limit]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<List> {
+        let (offset, limit) = parse_page(parse)?;
+        Ok(List { offset, limit })
+    }
+
+    /// Query the window and write the listing to `dst`.
+    #[instrument(skip(self, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let query = Query {
+            pattern: None,
+            offset: self.offset,
+            limit: self.limit,
+        };
+        query_window(query, tx, dst).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("list".as_bytes()));
+        frame.push_bulk(Bytes::from(self.offset.to_string()));
+        frame.push_bulk(Bytes::from(self.limit.to_string()));
+        frame
+    }
+}
+
+impl Search {
+    /// Create a new `Search` command for `pattern`.
+    pub fn new(pattern: impl ToString, offset: usize, limit: usize) -> Search {
+        Search {
+            pattern: pattern.to_string(),
+            offset,
+            limit,
+        }
+    }
+
+    /// Parse a `Search` instance from a received frame.
+    ///
+    /// The `SEARCH` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// SEARCH pattern [offset [limit]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Search> {
+        let pattern = parse.next_string()?;
+        let (offset, limit) = parse_page(parse)?;
+        Ok(Search {
+            pattern,
+            offset,
+            limit,
+        })
+    }
+
+    /// Query the window and write the matches to `dst`.
+    #[instrument(skip(self, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let query = Query {
+            pattern: Some(self.pattern),
+            offset: self.offset,
+            limit: self.limit,
+        };
+        query_window(query, tx, dst).await
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("search".as_bytes()));
+        frame.push_bulk(Bytes::from(self.pattern.into_bytes()));
+        frame.push_bulk(Bytes::from(self.offset.to_string()));
+        frame.push_bulk(Bytes::from(self.limit.to_string()));
+        frame
+    }
+}
+
+/// Read the optional offset and limit closing `LIST` and `SEARCH`.
+fn parse_page(parse: &mut Parse) -> crate::Result<(usize, usize)> {
+    let mut next = |default: usize| match parse.next_int() {
+        Ok(n) => Ok(n as usize),
+        Err(ParseError::EndOfStream) => Ok(default),
+        Err(err) => Err(err),
+    };
+    let offset = next(0)?;
+    let limit = next(DEFAULT_LIMIT)?;
+    Ok((offset, limit))
+}
+
+async fn query_window(
+    query: Query,
+    tx: Sender<WindowCommand>,
+    dst: &mut Connection,
+) -> crate::Result<()> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    tx.send(WindowCommand::List(query, reply_tx)).await?;
+    let listing = reply_rx.await?;
+
+    let response = listing_to_frame(&listing);
+    debug!(?response);
+
+    dst.write_frame(&response).await?;
+
+    Ok(())
+}
+
+/// Encode a listing as `[current, total, index, path, index, path, ...]`
+pub(crate) fn listing_to_frame(listing: &Listing) -> Frame {
+    let mut parts = vec![
+        Frame::Integer(listing.current as u64),
+        Frame::Integer(listing.total as u64),
+    ];
+    for (index, path) in &listing.entries {
+        parts.push(Frame::Integer(*index as u64));
+        parts.push(Frame::Bulk(Bytes::copy_from_slice(
+            path.as_os_str().as_bytes(),
+        )));
+    }
+    Frame::Array(parts)
+}
+
+/// Decode a listing encoded by [`listing_to_frame`]
+pub(crate) fn listing_from_frame(frame: Frame) -> crate::Result<Listing> {
+    let parts = match frame {
+        Frame::Array(parts) => parts,
+        frame => return Err(frame.to_error()),
+    };
+    let mut listing = Listing::default();
+    match parts.as_slice() {
+        [Frame::Integer(current), Frame::Integer(total), entries @ ..]
+            if entries.len() % 2 == 0 =>
+        {
+            listing.current = *current as usize;
+            listing.total = *total as usize;
+            for entry in entries.chunks(2) {
+                match entry {
+                    [Frame::Integer(index), Frame::Bulk(path)] => {
+                        let path = PathBuf::from(OsString::from_vec(path.to_vec()));
+                        listing.entries.push((*index as usize, path));
+                    }
+                    _ => return Err("protocol error; invalid listing entry".into()),
+                }
+            }
+            Ok(listing)
+        }
+        _ => Err(Frame::Array(parts).to_error()),
+    }
+}
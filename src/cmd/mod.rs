@@ -4,11 +4,37 @@ pub use get::Get;
 mod set;
 pub use set::Set;
 
+pub(crate) mod goto;
+pub use goto::Goto;
+
+mod filter;
+pub use filter::Filter;
+
+pub(crate) mod info;
+pub use info::Info;
+
+pub(crate) mod list;
+pub use list::{List, Search};
+
+mod reload;
+pub use reload::Reload;
+
+mod sort;
+pub use sort::Sort;
+
+mod subscribe;
+pub use subscribe::Subscribe;
+
+mod upload;
+pub use upload::{Upload, MAX_UPLOAD_SIZE, UPLOAD_DIR};
+
 mod unknown;
 
 pub use unknown::Unknown;
 
+use crate::serve::{Reloader, Role};
 use crate::{Connection, Db, Frame, Parse, Shutdown, WindowCommand};
+use std::path::Path;
 use tokio::sync::mpsc::Sender;
 
 /// Enumeration of supported Viewed commands.
@@ -18,6 +44,15 @@ use tokio::sync::mpsc::Sender;
 pub enum Command {
     Get(Get),
     Set(Set),
+    Upload(Upload),
+    Subscribe(Subscribe),
+    Goto(Goto),
+    List(List),
+    Search(Search),
+    Reload(Reload),
+    Info(Info),
+    Sort(Sort),
+    Filter(Filter),
     Unknown(Unknown),
 }
 
@@ -34,6 +69,15 @@ impl Command {
         let command = match &command_name[..] {
             "get" => Command::Get(Get::parse_frames(&mut parse)?),
             "set" => Command::Set(Set::parse_frames(&mut parse)?),
+            "upload" => Command::Upload(Upload::parse_frames(&mut parse)?),
+            "subscribe" => Command::Subscribe(Subscribe::parse_frames(&mut parse)?),
+            "goto" => Command::Goto(Goto::parse_frames(&mut parse)?),
+            "list" => Command::List(List::parse_frames(&mut parse)?),
+            "search" => Command::Search(Search::parse_frames(&mut parse)?),
+            "reload" => Command::Reload(Reload::parse_frames(&mut parse)?),
+            "info" => Command::Info(Info::parse_frames(&mut parse)?),
+            "sort" => Command::Sort(Sort::parse_frames(&mut parse)?),
+            "filter" => Command::Filter(Filter::parse_frames(&mut parse)?),
             _ => {
                 // The command is not recognized and an Unknown command is
                 // returned.
@@ -51,19 +95,73 @@ impl Command {
     }
 
     /// Apply the command to the SDL_Window by transmitting it back
-    /// through mpsc channel.
+    /// through mpsc channel. Clients whose `role` does not allow the
+    /// command get an error reply instead.
+    // each command only takes the parts of the connection state it needs
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn apply(
         self,
+        role: Role,
+        reloader: &Reloader,
         db: &Db,
+        root: &Path,
         tx: Sender<WindowCommand>,
         dst: &mut Connection,
-        _shutdown: &mut Shutdown,
+        shutdown: &mut Shutdown,
     ) -> crate::Result<()> {
         use Command::*;
+        let required = self.role();
+        if role < required {
+            let response = Frame::Error(format!(
+                "ERR permission denied; '{}' requires the {} role",
+                self.get_name(),
+                required
+            ));
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
         match self {
             Get(cmd) => cmd.apply(db, dst).await,
             Set(cmd) => cmd.apply(tx, dst).await,
+            Upload(cmd) => cmd.apply(root, tx, dst).await,
+            Subscribe(cmd) => cmd.apply(db, dst, shutdown).await,
+            Goto(cmd) => cmd.apply(tx, dst).await,
+            List(cmd) => cmd.apply(tx, dst).await,
+            Search(cmd) => cmd.apply(tx, dst).await,
+            Reload(cmd) => cmd.apply(reloader, tx, dst).await,
+            Info(cmd) => cmd.apply(db, tx, dst).await,
+            Sort(cmd) => cmd.apply(tx, dst).await,
+            Filter(cmd) => cmd.apply(tx, dst).await,
             Unknown(cmd) => cmd.apply(dst).await,
         }
     }
+
+    /// Least role a client needs to run the command
+    pub(crate) fn role(&self) -> Role {
+        use Command::*;
+        match self {
+            Get(_) | Subscribe(_) | List(_) | Search(_) | Info(_) | Unknown(_) => Role::Viewer,
+            Set(_) | Goto(_) | Sort(_) | Filter(_) => Role::Controller,
+            Upload(_) | Reload(_) => Role::Admin,
+        }
+    }
+
+    /// Returns the command name
+    pub(crate) fn get_name(&self) -> &str {
+        use Command::*;
+        match self {
+            Get(_) => "get",
+            Set(_) => "set",
+            Upload(_) => "upload",
+            Subscribe(_) => "subscribe",
+            Goto(_) => "goto",
+            List(_) => "list",
+            Search(_) => "search",
+            Reload(_) => "reload",
+            Info(_) => "info",
+            Sort(_) => "sort",
+            Filter(_) => "filter",
+            Unknown(cmd) => cmd.get_name(),
+        }
+    }
 }
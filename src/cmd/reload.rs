@@ -0,0 +1,111 @@
+use crate::cmd::goto::position_to_frame;
+use crate::parse::ParseError;
+use crate::serve::Reloader;
+use crate::window::WindowCommand;
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{debug, instrument};
+
+/// Rescan the image directory, or re-read the server config.
+///
+/// Changes are normally picked up as they happen, this forces a full
+/// rescan, for instance after the directory was modified while the
+/// watcher could not see it. The server replies with the position of the
+/// current image in the new collection.
+///
+/// `RELOAD CONFIG` re-reads the config file and certificates as on SIGHUP
+/// and replies with `OK`.
+#[derive(Debug, Default)]
+pub struct Reload {
+    /// What to reload, the image directory if `None`
+    target: Option<String>,
+}
+
+impl Reload {
+    /// Create a new `Reload` command which rescans the image directory.
+    pub fn new() -> Reload {
+        Reload { target: None }
+    }
+
+    /// Create a new `Reload` command which re-reads the server config.
+    pub fn config() -> Reload {
+        Reload {
+            target: Some("config".to_string()),
+        }
+    }
+
+    /// Parse a `Reload` instance from a received frame.
+    ///
+    /// The `RELOAD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// RELOAD [CONFIG]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Reload> {
+        let target = match parse.next_string() {
+            Ok(target) => Some(target),
+            Err(ParseError::EndOfStream) => None,
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Reload { target })
+    }
+
+    /// Ask the `SdlWindow` instance to rescan and write the resulting
+    /// position to `dst`, or reload the config with `reloader`.
+    #[instrument(skip(self, reloader, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        reloader: &Reloader,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        match self.target.as_deref().map(str::to_lowercase).as_deref() {
+            None => {}
+            Some("config") => {
+                let response = match reloader.reload().await {
+                    Ok(()) => Frame::Simple("OK".to_string()),
+                    Err(e) => Frame::Error(format!("ERR {}", e)),
+                };
+                debug!(?response);
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+            Some(target) => {
+                let response = Frame::Error(format!(
+                    "ERR unknown reload target '{}', expected 'config'",
+                    target
+                ));
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        }
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        tx.send(WindowCommand::Reload(reply_tx)).await?;
+
+        let response = match reply_rx.await? {
+            Ok(position) => position_to_frame(&position),
+            Err(e) => Frame::Error(format!("ERR {}", e)),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("reload".as_bytes()));
+        if let Some(target) = self.target {
+            frame.push_bulk(Bytes::from(target.into_bytes()));
+        }
+        frame
+    }
+}
@@ -1,5 +1,5 @@
 use crate::cmd::Parse;
-use crate::window::WindowCommand;
+use crate::window::{SetError, WindowCommand};
 use crate::{Connection, Frame};
 
 use bytes::Bytes;
@@ -7,10 +7,13 @@ use tokio::sync::mpsc::Sender;
 
 use tracing::{debug, instrument};
 
-/// We only store toggles
+/// Switch a toggle or change a window setting.
+///
+/// Toggles such as `fullscreen` ignore the value, settings such as `scale`
+/// are set to it.
 #[derive(Debug)]
 pub struct Set {
-    /// Toggle to switch
+    /// Toggle to switch or setting to change
     key: String,
     value: Bytes,
 }
@@ -59,7 +62,8 @@ impl Set {
     /// Transmit the `Set` command to the `SdlWindow` instance.
     ///
     /// The response is written to `dst`. This is called by the server in order
-    /// to execute a received command.
+    /// to execute a received command. Unknown keys and invalid values are
+    /// answered with an error and leave the connection open.
     #[instrument(skip(self, tx, dst))]
     pub(crate) async fn apply(
         self,
@@ -68,11 +72,20 @@ impl Set {
     ) -> crate::Result<()> {
         // get WindowCommand variant for command string
         // and transmit it to the Window
-        let cmd = WindowCommand::from_str(&self.key)?;
-        tx.send(cmd).await?;
-
-        // Create a success response and write it to `dst`.
-        let response = Frame::Simple("OK".to_string());
+        let cmd = match std::str::from_utf8(&self.value) {
+            Ok(value) => WindowCommand::from_str(&self.key, value),
+            Err(e) => Err(SetError::InvalidValue {
+                key: self.key.clone(),
+                reason: e.to_string(),
+            }),
+        };
+        let response = match cmd {
+            Ok(cmd) => {
+                tx.send(cmd).await?;
+                Frame::Simple("OK".to_string())
+            }
+            Err(e) => Frame::Error(format!("ERR {}", e)),
+        };
         debug!(?response);
 
         dst.write_frame(&response).await?;
@@ -0,0 +1,72 @@
+use crate::cmd::goto::position_to_frame;
+use crate::window::{SortOrder, WindowCommand};
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::{debug, instrument};
+
+/// Change the order of the collection.
+///
+/// The current image stays selected. The server replies with its position
+/// in the new order. The current order can be read with `GET sort`.
+#[derive(Debug)]
+pub struct Sort {
+    order: String,
+}
+
+impl Sort {
+    /// Create a new `Sort` command. See [`SortOrder`] for the accepted
+    /// orders.
+    pub fn new(order: impl ToString) -> Sort {
+        Sort {
+            order: order.to_string(),
+        }
+    }
+
+    /// Parse a `Sort` instance from a received frame.
+    ///
+    /// The `SORT` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// SORT order
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Sort> {
+        let order = parse.next_string()?;
+        Ok(Sort { order })
+    }
+
+    /// Transmit the new order to the `SdlWindow` instance and write the
+    /// resulting position to `dst`.
+    #[instrument(skip(self, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let response = match self.order.parse::<SortOrder>() {
+            Ok(order) => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                tx.send(WindowCommand::Sort(order, reply_tx)).await?;
+                position_to_frame(&reply_rx.await?)
+            }
+            Err(e) => Frame::Error(format!("ERR {}", e)),
+        };
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("sort".as_bytes()));
+        frame.push_bulk(Bytes::from(self.order.into_bytes()));
+        frame
+    }
+}
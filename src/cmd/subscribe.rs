@@ -0,0 +1,129 @@
+use std::os::unix::prelude::OsStringExt;
+
+use crate::parse::{Parse, ParseError};
+use crate::{Connection, Db, Frame, Shutdown};
+
+use bytes::Bytes;
+use std::ffi::OsString;
+use tracing::{debug, instrument};
+
+/// Subscribe the client to changes of the given keys.
+///
+/// Once the client enters the subscribed state, the server pushes a
+/// `message` frame every time one of the keys changes, starting with
+/// their current values. If no key is given, changes to all keys are
+/// sent. The subscription ends when the client closes the connection.
+#[derive(Debug)]
+pub struct Subscribe {
+    keys: Vec<String>,
+}
+
+impl Subscribe {
+    /// Create a new `Subscribe` command to listen on the specified keys.
+    pub fn new(keys: Vec<String>) -> Subscribe {
+        Subscribe { keys }
+    }
+
+    /// Parse a `Subscribe` instance from a received frame.
+    ///
+    /// The `SUBSCRIBE` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// SUBSCRIBE [key [key ...]]
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Subscribe> {
+        let mut keys = vec![];
+
+        // Read keys until the frame is consumed.
+        loop {
+            match parse.next_string() {
+                Ok(s) => keys.push(s),
+                Err(ParseError::EndOfStream) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Subscribe { keys })
+    }
+
+    /// Push updates to the client until it disconnects or the server
+    /// shuts down.
+    #[instrument(skip(self, db, dst, shutdown))]
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        dst: &mut Connection,
+        shutdown: &mut Shutdown,
+    ) -> crate::Result<()> {
+        // Subscribe before taking the snapshot so no update is missed.
+        let mut updates = db.subscribe();
+        self.send(db.entries(), dst).await?;
+
+        loop {
+            tokio::select! {
+                res = updates.recv() => match res {
+                    Some(updates) => {
+                        let updates = updates.into_iter().map(|u| (u.key, u.value));
+                        self.send(updates, dst).await?;
+                    }
+                    None => return Ok(()),
+                },
+                res = dst.read_frame() => match res? {
+                    Some(frame) => {
+                        debug!(?frame, "command in subscribe mode");
+                        let response = Frame::Error(
+                            "ERR connection is in subscribe mode".to_string(),
+                        );
+                        dst.write_frame(&response).await?;
+                    }
+                    // The client closed the connection
+                    None => return Ok(()),
+                },
+                _ = shutdown.recv() => return Ok(()),
+            }
+        }
+    }
+
+    /// Send the values of the keys the client subscribed to.
+    async fn send(
+        &self,
+        values: impl IntoIterator<Item = (String, OsString)>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        for (key, value) in values {
+            if self.wants(&key) {
+                dst.write_frame(&make_message_frame(key, value)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if changes to `key` should be pushed.
+    fn wants(&self, key: &str) -> bool {
+        self.keys.is_empty() || self.keys.iter().any(|k| k == key)
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding a `Subscribe` command to
+    /// send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("subscribe".as_bytes()));
+        for key in self.keys {
+            frame.push_bulk(Bytes::from(key.into_bytes()));
+        }
+        frame
+    }
+}
+
+/// Creates the message informing the client about a changed key.
+fn make_message_frame(key: String, value: OsString) -> Frame {
+    let mut response = Frame::array();
+    response.push_bulk(Bytes::from_static(b"message"));
+    response.push_bulk(Bytes::from(key.into_bytes()));
+    response.push_bulk(Bytes::from(value.into_vec()));
+    response
+}
@@ -0,0 +1,206 @@
+use crate::frame::MAX_CHUNK_SIZE;
+use crate::window::WindowCommand;
+use crate::{Connection, Frame, Parse};
+
+use bytes::Bytes;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, instrument};
+
+/// Directory under the server `--path` receiving uploaded images
+pub const UPLOAD_DIR: &str = "uploads";
+
+/// Largest file accepted by `UPLOAD`
+pub const MAX_UPLOAD_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Numbers the partial files, so uploads of the same name do not write
+/// to the same one
+static UPLOADS: AtomicU64 = AtomicU64::new(0);
+
+/// Upload an image to the server.
+///
+/// The command announces the file name and size. Once the server has
+/// replied `OK` the client streams the content as `DataChunk`
+/// frames. The file is written to [`UPLOAD_DIR`] and optionally
+/// displayed once it is complete.
+#[derive(Debug)]
+pub struct Upload {
+    /// File name of the image on the server
+    name: String,
+    /// Total number of bytes that will follow
+    size: u64,
+    /// Display the image once received
+    show: bool,
+}
+
+impl Upload {
+    /// Create a new `Upload` command announcing `size` bytes of `name`.
+    pub fn new(name: impl ToString, size: u64, show: bool) -> Upload {
+        Upload {
+            name: name.to_string(),
+            size,
+            show,
+        }
+    }
+
+    /// Parse an `Upload` instance from a received frame.
+    ///
+    /// The `UPLOAD` string has already been consumed.
+    ///
+    /// # Format
+    ///
+    /// ```text
+    /// UPLOAD name size show
+    /// ```
+    pub(crate) fn parse_frames(parse: &mut Parse) -> crate::Result<Upload> {
+        let name = parse.next_string()?;
+        let size = parse.next_int()?;
+        let show = parse.next_int()? != 0;
+        Ok(Upload { name, size, show })
+    }
+
+    /// Receive the file and write it under `root`.
+    ///
+    /// Data is first written to a hidden partial file which is
+    /// linked into place once all chunks have arrived, so an
+    /// interrupted upload never shows up as a broken image. An
+    /// existing image of the same name is never replaced.
+    #[instrument(skip(self, root, tx, dst))]
+    pub(crate) async fn apply(
+        self,
+        root: &Path,
+        tx: Sender<WindowCommand>,
+        dst: &mut Connection,
+    ) -> crate::Result<()> {
+        let name = match file_name(&self.name) {
+            Some(name) => name,
+            None => {
+                let response = Frame::Error(format!("ERR invalid file name '{}'", self.name));
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+        };
+        if self.size > MAX_UPLOAD_SIZE {
+            let response = Frame::Error(format!("ERR upload exceeds {} bytes", MAX_UPLOAD_SIZE));
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+
+        let dir = root.join(UPLOAD_DIR);
+        fs::create_dir_all(&dir).await?;
+        let target = dir.join(&name);
+        if fs::symlink_metadata(&target).await.is_ok() {
+            let response = Frame::Error(format!("ERR '{}' already exists", name.display()));
+            dst.write_frame(&response).await?;
+            return Ok(());
+        }
+        let id = UPLOADS.fetch_add(1, Ordering::Relaxed);
+        let part = Part(dir.join(format!(".{}.{}.part", name.display(), id)));
+
+        // Tell the client to start streaming
+        dst.write_frame(&Frame::Simple("OK".to_string())).await?;
+
+        receive(&part.0, self.size, dst).await?;
+        // unlike renaming, linking fails if another upload of the same
+        // name finished meanwhile
+        match fs::hard_link(&part.0, &target).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                let response = Frame::Error(format!("ERR '{}' already exists", name.display()));
+                dst.write_frame(&response).await?;
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        }
+        debug!("received {}", target.display());
+
+        if self.show {
+            tx.send(WindowCommand::Open(target)).await?;
+        }
+
+        let response = Frame::Simple("OK".to_string());
+        debug!(?response);
+
+        dst.write_frame(&response).await?;
+
+        Ok(())
+    }
+
+    /// Converts the command into an equivalent `Frame`.
+    ///
+    /// This is called by the client when encoding an `Upload` command
+    /// to send to the server.
+    pub(crate) fn into_frame(self) -> Frame {
+        let mut frame = Frame::array();
+        frame.push_bulk(Bytes::from("upload".as_bytes()));
+        frame.push_bulk(Bytes::from(self.name.into_bytes()));
+        frame.push_bulk(Bytes::from(self.size.to_string().into_bytes()));
+        frame.push_bulk(Bytes::from(if self.show { "1" } else { "0" }));
+        frame
+    }
+}
+
+/// Partial file of an upload, removed when dropped. Once the upload is
+/// complete the image is linked to it, otherwise it failed or was
+/// cancelled because the connection was cut off.
+struct Part(PathBuf);
+
+impl Drop for Part {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Read `size` bytes worth of `DataChunk` frames from `src` into `path`.
+async fn receive(path: &Path, size: u64, src: &mut Connection) -> crate::Result<()> {
+    let mut file = File::create(path).await?;
+    let mut received = 0;
+
+    while received < size {
+        let chunk = match src.read_frame().await? {
+            Some(Frame::DataChunk { chunk }) => chunk,
+            Some(frame) => return Err(frame.to_error()),
+            None => return Err("connection closed during upload".into()),
+        };
+        if chunk.len() > MAX_CHUNK_SIZE || received + chunk.len() as u64 > size {
+            return Err("protocol error; upload larger than announced".into());
+        }
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+    }
+    file.flush().await?;
+
+    Ok(())
+}
+
+/// Strip any directory components so uploads can not escape the
+/// staging directory.
+fn file_name(name: &str) -> Option<PathBuf> {
+    let name = Path::new(name).file_name()?;
+    if name.to_string_lossy().starts_with('.') {
+        return None;
+    }
+    Some(PathBuf::from(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("a.png"), Some(PathBuf::from("a.png")));
+        assert_eq!(file_name("../x"), Some(PathBuf::from("x")));
+        assert_eq!(file_name("a/b"), Some(PathBuf::from("b")));
+        assert_eq!(file_name("/etc/passwd"), Some(PathBuf::from("passwd")));
+        assert_eq!(file_name(".hidden"), None);
+        assert_eq!(file_name("a/.b.png.0.part"), None);
+        assert_eq!(file_name(".."), None);
+        assert_eq!(file_name("a/.."), None);
+        assert_eq!(file_name(""), None);
+    }
+}
@@ -157,7 +157,14 @@ impl Connection {
                 self.stream.write_all(b"\r\n").await?;
             }
 
-            Frame::DataChunk { chunk: _ } => todo!(),
+            Frame::DataChunk { chunk } => {
+                let len = chunk.len();
+
+                self.stream.write_u8(b'#').await?;
+                self.write_decimal(len as u64).await?;
+                self.stream.write_all(chunk).await?;
+                self.stream.write_all(b"\r\n").await?;
+            }
             Frame::Array(_val) => unreachable!(),
         }
         Ok(())
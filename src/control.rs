@@ -1,39 +1,116 @@
+use anyhow::anyhow;
+use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::net::TcpListener;
 use tokio::signal;
 use tokio::sync::mpsc::channel;
+use tracing::error;
 
 use crate::db::DbDropGuard;
-use crate::sdl_window::SdlWindow;
-use crate::serve::{get_acceptor, Config};
+use crate::sdl_window::{Display, Window};
+use crate::serve::{Config, Reloader, Tls};
 use crate::server;
+use crate::watcher;
+use crate::window::WindowCommand;
 
-/// Spawns TPCListener task and initialized SdlWindow control loop. Database
-/// is initialized and passed to both for shared state. Mpsc channel is used to
-/// transmit commands from Tcp handler to Sdl Window.
-pub async fn run<A: ToSocketAddrs>(
-    addr: A,
+/// Spawns TPCListener task and initialized Window control loop, showing the
+/// images on `display`. Database is initialized and passed to both for
+/// shared state. Mpsc channel is used to transmit commands from Tcp handler
+/// to the Window. A listener is bound for
+/// each of `addrs`, all of them feeding the same window.
+///
+/// SIGHUP reloads `config_path`, see [`Reloader`].
+pub async fn run(
+    addrs: &[SocketAddr],
     path: &Path,
+    config_path: &Path,
     config: Arc<Config>,
+    display: impl Display,
 ) -> anyhow::Result<()> {
     let db_holder = DbDropGuard::new();
     let db = db_holder.db();
 
-    let (win_cmd_tx, win_cmd_rx) = channel(32);
-    let listener = TcpListener::bind(addr).await?;
+    let (win_cmd_tx, mut commands) = channel(32);
+    if addrs.is_empty() {
+        return Err(anyhow!("no bind address given"));
+    }
+    let mut listeners = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| anyhow!("could not bind {}: {}", addr, e))?;
+        listeners.push(listener);
+    }
 
     // get TLS acceptor
-    let acceptor = get_acceptor(config.clone())?;
+    let tls = Arc::new(Tls::new(config.clone())?);
 
+    let reloader = Arc::new(Reloader::new(
+        config_path.to_path_buf(),
+        config.clone(),
+        tls.clone(),
+        win_cmd_tx.clone(),
+    ));
+    reloader.watch_crl(&config)?;
+    reload_on_hangup(reloader.clone())?;
+
+    // keep the collection in sync with the image directory for as long
+    // as the window runs
+    let _watcher = watcher::watch(path, win_cmd_tx.clone())?;
+
+    // the window does not watch for ctrl-c itself when it is headless
+    let quit_tx = win_cmd_tx.clone();
     tokio::spawn(async move {
-        server::run(listener, db_holder, win_cmd_tx, acceptor, signal::ctrl_c()).await;
+        if signal::ctrl_c().await.is_ok() {
+            let _ = quit_tx.send(WindowCommand::Quit).await;
+        }
     });
 
-    let mut window = SdlWindow::new("viewd", path, win_cmd_rx, db, config)?;
+    // the window sleeps until something happens, so wake it up for every
+    // command
+    let (forward_tx, win_cmd_rx) = channel(32);
+    let wake = display.waker();
+    tokio::spawn(async move {
+        while let Some(command) = commands.recv().await {
+            if forward_tx.send(command).await.is_err() {
+                break;
+            }
+            wake();
+        }
+    });
+
+    let root = path.to_path_buf();
+    tokio::spawn(async move {
+        server::run(
+            listeners,
+            db_holder,
+            win_cmd_tx,
+            tls,
+            reloader,
+            root,
+            signal::ctrl_c(),
+        )
+        .await;
+    });
+
+    let mut window = Window::new(display, path, win_cmd_rx, db, config)?;
 
     window.init()?;
     window.handle_event()?;
 
     Ok(())
 }
+
+/// Reload the config whenever the process receives SIGHUP
+fn reload_on_hangup(reloader: Arc<Reloader>) -> anyhow::Result<()> {
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            if let Err(e) = reloader.reload().await {
+                error!("could not reload config: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
@@ -1,9 +1,13 @@
-use tokio::sync::Notify;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Notify};
+use tracing::debug;
 
 use std::collections::HashMap;
 use std::ffi::OsString;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub struct DbDropGuard {
@@ -23,6 +27,38 @@ pub struct Db {
 struct Shared {
     state: Mutex<State>,
     background_task: Notify,
+    /// Changed entries are broadcast to subscribed connections.
+    updates: broadcast::Sender<Update>,
+    /// When the server started
+    started: Instant,
+    /// Number of connected clients
+    clients: AtomicUsize,
+}
+
+/// Counts a client as connected until dropped
+#[derive(Debug)]
+pub(crate) struct ClientGuard {
+    db: Db,
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        self.db.shared.clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A key which was set to a new value
+#[derive(Debug, Clone)]
+pub(crate) struct Update {
+    pub key: String,
+    pub value: OsString,
+}
+
+/// Updates to the db as seen by one subscriber
+#[derive(Debug)]
+pub(crate) struct Subscription {
+    db: Db,
+    rx: broadcast::Receiver<Update>,
 }
 
 #[derive(Debug)]
@@ -62,6 +98,9 @@ impl Db {
                 shutdown: false,
             }),
             background_task: Notify::new(),
+            updates: broadcast::channel(32).0,
+            started: Instant::now(),
+            clients: AtomicUsize::new(0),
         });
 
         Db { shared }
@@ -73,13 +112,57 @@ impl Db {
         state.entries.get(key).cloned()
     }
 
-    /// Set the value associated with a key along with an optional expiration
-    /// Duration.
+    /// Get all keys and their values.
+    pub(crate) fn entries(&self) -> Vec<(String, OsString)> {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .entries
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Set the value associated with a key.
     ///
-    /// If a value is already associated with the key, it is removed.
+    /// If a value is already associated with the key, it is replaced. When the
+    /// value changes, subscribers are notified.
     pub(crate) fn set(&self, key: String, value: OsString) {
         let mut state = self.shared.state.lock().unwrap();
-        state.entries.insert(key, value);
+        if state.entries.get(&key) == Some(&value) {
+            return;
+        }
+        state.entries.insert(key.clone(), value.clone());
+        drop(state);
+
+        // An error only means there are no subscribers.
+        let _ = self.shared.updates.send(Update { key, value });
+    }
+
+    /// Subscribe to updates of any key.
+    ///
+    /// Subscribe before taking a snapshot with `entries` so no update is
+    /// missed in between.
+    pub(crate) fn subscribe(&self) -> Subscription {
+        Subscription {
+            db: self.clone(),
+            rx: self.shared.updates.subscribe(),
+        }
+    }
+
+    /// Record a connected client for as long as the returned guard lives.
+    pub(crate) fn connected(&self) -> ClientGuard {
+        self.shared.clients.fetch_add(1, Ordering::Relaxed);
+        ClientGuard { db: self.clone() }
+    }
+
+    /// Number of connected clients.
+    pub(crate) fn clients(&self) -> usize {
+        self.shared.clients.load(Ordering::Relaxed)
+    }
+
+    /// Time since the server started.
+    pub(crate) fn uptime(&self) -> Duration {
+        self.shared.started.elapsed()
     }
 
     /// Signals the purge background task to shut down. This is called by the
@@ -97,3 +180,79 @@ impl Db {
         self.shared.background_task.notify_one();
     }
 }
+
+impl Subscription {
+    /// Wait for the next updates.
+    ///
+    /// A subscriber falling so far behind that updates were dropped gets
+    /// the current value of every key instead, and continues with the
+    /// updates made after that. Returns `None` if no more updates can
+    /// arrive.
+    pub(crate) async fn recv(&mut self) -> Option<Vec<Update>> {
+        match self.rx.recv().await {
+            Ok(update) => Some(vec![update]),
+            Err(RecvError::Lagged(n)) => {
+                debug!("subscriber lagged by {}", n);
+                // Skip the stale updates still queued, they would replace
+                // the current values again.
+                self.rx = self.rx.resubscribe();
+                let entries = self.db.entries();
+                Some(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| Update { key, value })
+                        .collect(),
+                )
+            }
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(updates: Vec<Update>) -> Vec<(String, OsString)> {
+        let mut values: Vec<_> = updates.into_iter().map(|u| (u.key, u.value)).collect();
+        values.sort();
+        values
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let db = Db::new();
+        db.set("a".into(), "1".into());
+        let mut updates = db.subscribe();
+        assert_eq!(db.entries(), vec![("a".into(), "1".into())]);
+
+        db.set("a".into(), "1".into());
+        db.set("a".into(), "2".into());
+        db.set("b".into(), "3".into());
+        let next = updates.recv().await.unwrap();
+        assert_eq!(values(next), vec![("a".into(), "2".into())]);
+        let next = updates.recv().await.unwrap();
+        assert_eq!(values(next), vec![("b".into(), "3".into())]);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_lagged() {
+        let db = Db::new();
+        db.set("a".into(), "1".into());
+        let mut updates = db.subscribe();
+        for i in 0..40 {
+            db.set("b".into(), i.to_string().into());
+        }
+
+        let snapshot = updates.recv().await.unwrap();
+        assert_eq!(
+            values(snapshot),
+            vec![("a".into(), "1".into()), ("b".into(), "39".into())]
+        );
+
+        // Only updates made after the snapshot follow.
+        db.set("a".into(), "2".into());
+        let next = updates.recv().await.unwrap();
+        assert_eq!(values(next), vec![("a".into(), "2".into())]);
+    }
+}
@@ -14,14 +14,17 @@ pub enum Frame {
     Array(Vec<Frame>),
     Integer(u64),
     Bulk(Bytes),
-    /// Used for file transfer
+    /// Used for file transfer. A chunk may hold at most
+    /// [`MAX_CHUNK_SIZE`] bytes.
     DataChunk {
-        // unimplemented
         chunk: Bytes,
     },
     Null,
 }
 
+/// Largest payload accepted in a single `DataChunk` frame
+pub const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
 #[derive(Debug)]
 pub enum Error {
     /// Not enough data is available to parse a message
@@ -54,10 +57,14 @@ impl Frame {
     pub fn check(src: &mut Cursor<&[u8]>) -> Result<(), Error> {
         // type of frame is first u8 value
         match get_u8(src)? {
-            b'+' => {
+            b'+' | b'-' => {
                 get_line(src)?;
                 Ok(())
             }
+            b':' => {
+                let _ = get_decimal(src)?;
+                Ok(())
+            }
             b'$' => {
                 if b'-' == peek_u8(src)? {
                     // Skip '-1\r\n'
@@ -70,6 +77,16 @@ impl Frame {
                     skip(src, len + 2)
                 }
             }
+            b'#' => {
+                let len: usize = get_decimal(src)?.try_into()?;
+
+                // Refuse oversized chunks before buffering them
+                if len > MAX_CHUNK_SIZE {
+                    return Err("protocol error; data chunk too large".into());
+                }
+
+                skip(src, len + 2)
+            }
             b'*' => {
                 let len = get_decimal(src)?;
 
@@ -94,6 +111,16 @@ impl Frame {
 
                 Ok(Frame::Simple(string))
             }
+            b'-' => {
+                let line = get_line(src)?.to_vec();
+                let string = String::from_utf8(line)?;
+
+                Ok(Frame::Error(string))
+            }
+            b':' => {
+                let value = get_decimal(src)?;
+                Ok(Frame::Integer(value))
+            }
             b'$' => {
                 if b'-' == peek_u8(src)? {
                     let line = get_line(src)?;
@@ -119,6 +146,20 @@ impl Frame {
                     Ok(Frame::Bulk(data))
                 }
             }
+            b'#' => {
+                let len = get_decimal(src)?.try_into()?;
+                let n = len + 2;
+
+                if src.remaining() < n {
+                    return Err(Error::Incomplete);
+                }
+
+                let chunk = Bytes::copy_from_slice(&src.chunk()[..len]);
+
+                skip(src, n)?;
+
+                Ok(Frame::DataChunk { chunk })
+            }
 
             b'*' => {
                 let len = get_decimal(src)?.try_into()?;
@@ -131,7 +172,7 @@ impl Frame {
                 Ok(Frame::Array(out))
             }
 
-            _ => unimplemented!(),
+            actual => Err(format!("protocol error; invalid frame type byte `{}`", actual).into()),
         }
     }
     /// Converts the frame to an "unexpected frame" error
@@ -238,3 +279,53 @@ impl fmt::Display for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_type_byte() {
+        let mut src = Cursor::new(&b"?foo\r\n"[..]);
+        assert!(matches!(Frame::parse(&mut src), Err(Error::Other(_))));
+        let mut src = Cursor::new(&b"*1\r\n?foo\r\n"[..]);
+        assert!(matches!(Frame::check(&mut src), Err(Error::Other(_))));
+    }
+
+    /// Encode a `DataChunk` frame as `Connection::write_frame` does
+    fn data_chunk(chunk: &[u8]) -> Vec<u8> {
+        let mut buf = format!("#{}\r\n", chunk.len()).into_bytes();
+        buf.extend_from_slice(chunk);
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+
+    #[test]
+    fn test_data_chunk() {
+        let buf = data_chunk(b"\r\n#2\r\n");
+        let mut src = Cursor::new(&buf[..]);
+        Frame::check(&mut src).unwrap();
+        assert_eq!(src.position() as usize, buf.len());
+
+        let mut src = Cursor::new(&buf[..]);
+        match Frame::parse(&mut src).unwrap() {
+            Frame::DataChunk { chunk } => assert_eq!(&chunk[..], b"\r\n#2\r\n"),
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+
+        // a chunk split across reads waits for the rest
+        let mut src = Cursor::new(&buf[..buf.len() - 1]);
+        assert!(matches!(Frame::check(&mut src), Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_data_chunk_size() {
+        let buf = data_chunk(&vec![0; MAX_CHUNK_SIZE]);
+        Frame::check(&mut Cursor::new(&buf[..])).unwrap();
+
+        // refused from the header alone, before the payload arrives
+        let buf = format!("#{}\r\n", MAX_CHUNK_SIZE + 1);
+        let mut src = Cursor::new(buf.as_bytes());
+        assert!(matches!(Frame::check(&mut src), Err(Error::Other(_))));
+    }
+}
@@ -0,0 +1,156 @@
+//! Minimal glob matching for image paths.
+//!
+//! Supports `*` (any run of characters except `/`), `**` (any run of
+//! characters including `/`), `?` (a single character except `/`) and
+//! character classes such as `[abc]`, `[a-z]` or `[!0-9]`.
+
+/// Returns `true` if `pattern` contains glob meta characters.
+pub(crate) fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Match `text` against the glob `pattern`.
+///
+/// Patterns without a `/` are matched against the last path component of
+/// `text` only, so `*.png` matches `a/b/c.png`.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let text = if pattern.contains('/') {
+        text
+    } else {
+        text.rsplit('/').next().unwrap_or(text)
+    };
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    let mut matcher = Matcher {
+        pattern,
+        text,
+        memo: vec![None; 2 * (pattern.len() + 1) * (text.len() + 1)],
+    };
+    matcher.at(0, 0, false)
+}
+
+/// Matches a pattern against a text, remembering the outcome for every
+/// position in both, so patterns full of stars take polynomial rather
+/// than exponential time.
+struct Matcher<'a> {
+    pattern: &'a [char],
+    text: &'a [char],
+    memo: Vec<Option<bool>>,
+}
+
+impl Matcher<'_> {
+    /// Check if `pattern[p..]` matches `text[t..]`. `inside` is set once
+    /// the `**` at `p` has consumed characters.
+    fn at(&mut self, p: usize, t: usize, inside: bool) -> bool {
+        let key = (p * (self.text.len() + 1) + t) * 2 + inside as usize;
+        if let Some(matched) = self.memo[key] {
+            return matched;
+        }
+        let matched = self.step(p, t, inside);
+        self.memo[key] = Some(matched);
+        matched
+    }
+
+    fn step(&mut self, p: usize, t: usize, inside: bool) -> bool {
+        let (pattern, text) = (self.pattern, self.text);
+        match pattern.get(p) {
+            None => t == text.len(),
+            Some('*') if pattern.get(p + 1) == Some(&'*') => {
+                // `**/` also matches no directory at all
+                (!inside && pattern.get(p + 2) == Some(&'/') && self.at(p + 3, t, false))
+                    || self.at(p + 2, t, false)
+                    || (t < text.len() && self.at(p, t + 1, true))
+            }
+            Some('*') => {
+                self.at(p + 1, t, false)
+                    || (text.get(t).is_some_and(|c| *c != '/') && self.at(p, t + 1, false))
+            }
+            Some('?') => text.get(t).is_some_and(|c| *c != '/') && self.at(p + 1, t + 1, false),
+            Some('[') => match (class(&pattern[p + 1..]), text.get(t)) {
+                (Some((matched, len)), Some(c)) if *c != '/' && matched(*c) => {
+                    self.at(p + len + 1, t + 1, false)
+                }
+                // an unterminated class is a literal `[`
+                (None, Some('[')) => self.at(p + 1, t + 1, false),
+                _ => false,
+            },
+            Some(c) => text.get(t) == Some(c) && self.at(p + 1, t + 1, false),
+        }
+    }
+}
+
+/// Parse a character class following a `[`. Returns a predicate and the
+/// number of pattern characters consumed, including the closing `]`.
+fn class(pattern: &[char]) -> Option<(impl Fn(char) -> bool, usize)> {
+    let (negated, start) = match pattern.first() {
+        Some('!') | Some('^') => (true, 1),
+        _ => (false, 0),
+    };
+    // a `]` right after the opening bracket is part of the class
+    let end = start + 1 + pattern.get(start + 1..)?.iter().position(|c| *c == ']')?;
+    let set: Vec<char> = pattern[start..end].to_vec();
+    let matched = move |c: char| {
+        let mut i = 0;
+        let mut found = false;
+        while i < set.len() {
+            if i + 2 < set.len() && set[i + 1] == '-' {
+                found |= set[i] <= c && c <= set[i + 2];
+                i += 3;
+            } else {
+                found |= set[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matched, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_star() {
+        assert!(glob_match("*.png", "a.png"));
+        assert!(glob_match("*.png", "dir/a.png"));
+        assert!(!glob_match("*.png", "a.jpg"));
+        assert!(glob_match("dir/*.png", "dir/a.png"));
+        assert!(!glob_match("dir/*.png", "dir/sub/a.png"));
+        assert!(glob_match("dir/**/*.png", "dir/sub/a.png"));
+        assert!(glob_match("dir/**/*.png", "dir/a.png"));
+        assert!(glob_match("**/thumbs/*", "a/b/thumbs/c.jpg"));
+        assert!(glob_match("dir/**/b.png", "dir/b.png"));
+        assert!(!glob_match("dir/**/b.png", "dir/xb.png"));
+    }
+
+    #[test]
+    fn test_glob_question_and_class() {
+        assert!(glob_match("img-??.jpg", "img-01.jpg"));
+        assert!(!glob_match("img-??.jpg", "img-1.jpg"));
+        assert!(glob_match("img-[0-9].jpg", "img-7.jpg"));
+        assert!(!glob_match("img-[!0-9].jpg", "img-7.jpg"));
+        assert!(glob_match("img-[!0-9].jpg", "img-x.jpg"));
+        assert!(glob_match("[]x].jpg", "].jpg"));
+        assert!(glob_match("a[.jpg", "a[.jpg"));
+    }
+
+    #[test]
+    fn test_glob_many_stars() {
+        let name = format!("{}.jpg", "a".repeat(200));
+        assert!(!glob_match("*a*a*a*a*a*a*a*a*a*a*b", &name));
+        assert!(!glob_match("**a**a**a**a**a**a**a**b", &name));
+        assert!(glob_match("*a*a*a*a*a*a*a*a*a*a*.jpg", &name));
+    }
+
+    #[test]
+    fn test_is_glob() {
+        assert!(is_glob("*.jpg"));
+        assert!(is_glob("a?"));
+        assert!(!is_glob("beach"));
+    }
+}
@@ -1,8 +1,14 @@
+// The protocol code is shared by the server and the clients, each of which
+// only uses its own half of it when built without the other.
+#![cfg_attr(not(all(feature = "server", feature = "client")), allow(dead_code))]
+
 pub mod serve;
 
 pub use serve::Listener;
 
+#[cfg(feature = "client")]
 pub mod clients;
+#[cfg(feature = "client")]
 pub use clients::Client;
 
 pub mod cmd;
@@ -23,15 +29,29 @@ use shutdown::Shutdown;
 mod parse;
 use parse::Parse;
 
+mod glob;
+
+mod pem;
+
+mod shuffle;
+
+#[cfg(feature = "server")]
+mod watcher;
+
+#[cfg(feature = "server")]
 pub mod control;
+#[cfg(feature = "server")]
 pub mod server;
 
 pub mod window;
 use window::WindowCommand;
 
+#[cfg(feature = "server")]
 pub mod sdl_window;
 
+#[cfg(feature = "tui")]
 pub mod tui;
+#[cfg(feature = "tui")]
 pub use tui::Tui;
 
 /// Used if no port is specified.
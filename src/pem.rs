@@ -0,0 +1,136 @@
+//! Loading of PEM encoded certificates, keys and revocation lists, shared
+//! by the server and the clients.
+
+use anyhow::{anyhow, Result};
+use rustls_pemfile::{read_one, Item};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore};
+
+/// Certificates in the PEM file at `path`
+pub(crate) fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let items = read_items(path)?;
+    let certs: Vec<Certificate> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::X509Certificate(der) => Some(Certificate(der.clone())),
+            _ => None,
+        })
+        .collect();
+    if certs.is_empty() {
+        return Err(anyhow!(
+            "no certificate found in {}, found {}",
+            path.display(),
+            describe(&items)
+        ));
+    }
+    Ok(certs)
+}
+
+/// Certificate authorities trusted to sign peer certificates
+pub(crate) fn load_roots(path: &Path) -> Result<RootCertStore> {
+    let mut store = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        store
+            .add(&cert)
+            .map_err(|e| anyhow!("invalid CA certificate in {}: {}", path.display(), e))?;
+    }
+    Ok(store)
+}
+
+/// First private key in the PEM file at `path`. PKCS#8, PKCS#1 RSA and
+/// SEC1 EC keys are accepted.
+pub(crate) fn load_key(path: &Path) -> Result<PrivateKey> {
+    let items = read_items(path)?;
+    key(&items).ok_or_else(|| {
+        anyhow!(
+            "no private key found in {}, found {}",
+            path.display(),
+            describe(&items)
+        )
+    })
+}
+
+/// Revocation lists in the file at `path`, PEM encoded or a single DER
+/// encoded one
+pub(crate) fn load_crls(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let mut bytes = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .map_err(|e| anyhow!("could not read {}: {}", path.display(), e))?;
+    let crls = rustls_pemfile::crls(&mut &bytes[..])?;
+    if crls.is_empty() {
+        return Ok(vec![bytes]);
+    }
+    Ok(crls)
+}
+
+fn read_items(path: &Path) -> Result<Vec<Item>> {
+    let file = File::open(path).map_err(|e| anyhow!("could not open {}: {}", path.display(), e))?;
+    parse(&mut BufReader::new(file))
+        .map_err(|e| anyhow!("invalid PEM in {}: {}", path.display(), e))
+}
+
+/// Every item of a PEM stream, skipping sections of unknown type
+fn parse(reader: &mut dyn BufRead) -> std::io::Result<Vec<Item>> {
+    std::iter::from_fn(|| read_one(reader).transpose()).collect()
+}
+
+fn key(items: &[Item]) -> Option<PrivateKey> {
+    items.iter().find_map(|item| match item {
+        Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der.clone())),
+        _ => None,
+    })
+}
+
+/// What a PEM file holds, for error messages
+fn describe(items: &[Item]) -> String {
+    if items.is_empty() {
+        return "nothing".to_string();
+    }
+    let names: Vec<&str> = items
+        .iter()
+        .map(|item| match item {
+            Item::X509Certificate(_) => "a certificate",
+            Item::RSAKey(_) => "a PKCS#1 RSA key",
+            Item::PKCS8Key(_) => "a PKCS#8 key",
+            Item::ECKey(_) => "a SEC1 EC key",
+            Item::Crl(_) => "a certificate revocation list",
+            _ => "an unsupported item",
+        })
+        .collect();
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pem(label: &str) -> String {
+        format!("-----BEGIN {0}-----\nAAEC\n-----END {0}-----\n", label)
+    }
+
+    #[test]
+    fn test_key_types() -> std::io::Result<()> {
+        for label in ["PRIVATE KEY", "RSA PRIVATE KEY", "EC PRIVATE KEY"] {
+            let text = pem("CERTIFICATE") + &pem(label);
+            let items = parse(&mut text.as_bytes())?;
+            assert_eq!(key(&items), Some(PrivateKey(vec![0, 1, 2])), "{}", label);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_describe() -> std::io::Result<()> {
+        let text = pem("CERTIFICATE") + &pem("X509 CRL");
+        let items = parse(&mut text.as_bytes())?;
+        assert_eq!(key(&items), None);
+        assert_eq!(
+            describe(&items),
+            "a certificate, a certificate revocation list"
+        );
+        assert_eq!(describe(&[]), "nothing");
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::window::AnimationControl;
+
+/// Plays the frames of an animated image
+#[derive(Debug, Clone)]
+pub struct Playback {
+    /// image being played
+    path: PathBuf,
+    /// how long each frame is shown, empty for still images
+    delays: Vec<Duration>,
+    /// index of the frame shown
+    frame: usize,
+    /// when the frame was shown
+    since: Instant,
+    /// complete loops shown so far
+    loops: u32,
+    paused: bool,
+}
+
+impl Playback {
+    /// Start playing the image at `path` with the frame `delays` at `now`
+    pub fn new(path: &Path, delays: Vec<Duration>, now: Instant) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            delays,
+            frame: 0,
+            since: now,
+            loops: 0,
+            paused: false,
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+    pub fn frames(&self) -> usize {
+        self.delays.len().max(1)
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    fn is_animated(&self) -> bool {
+        self.delays.len() > 1
+    }
+    /// Time left at `now` until the next frame is due, `None` for still
+    /// images and paused animations
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        if !self.is_animated() || self.paused {
+            return None;
+        }
+        let shown = now.saturating_duration_since(self.since);
+        Some(self.delays[self.frame].saturating_sub(shown))
+    }
+    /// Move on to the next frame if it is due at `now`. Returns whether the
+    /// frame changed.
+    pub fn advance(&mut self, now: Instant) -> bool {
+        if self.remaining(now) != Some(Duration::ZERO) {
+            return false;
+        }
+        self.frame = (self.frame + 1) % self.delays.len();
+        if self.frame == 0 {
+            self.loops += 1;
+        }
+        self.since = now;
+        true
+    }
+    /// Apply a change requested by a client at `now`
+    pub fn control(&mut self, control: AnimationControl, now: Instant) {
+        match control {
+            AnimationControl::Pause => self.paused = true,
+            AnimationControl::Resume => self.resume(now),
+            AnimationControl::Toggle if self.paused => self.resume(now),
+            AnimationControl::Toggle => self.paused = true,
+            AnimationControl::Next => self.step(1),
+            AnimationControl::Prev => self.step(self.frames() - 1),
+        }
+    }
+    /// Whether pageant mode may move on to the next image. Animations are
+    /// shown for at least one full loop, unless they are paused.
+    pub fn looped(&self) -> bool {
+        !self.is_animated() || self.paused || self.loops > 0
+    }
+    fn resume(&mut self, now: Instant) {
+        if self.paused {
+            self.paused = false;
+            // the frame is shown for its full delay again
+            self.since = now;
+        }
+    }
+    fn step(&mut self, by: usize) {
+        self.paused = true;
+        self.frame = (self.frame + by) % self.frames();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_playback_loops() {
+        let start = Instant::now();
+        let mut playback = Playback::new(Path::new("a.gif"), vec![ms(100), ms(50)], start);
+        assert_eq!(playback.remaining(start + ms(40)), Some(ms(60)));
+        assert!(!playback.advance(start + ms(99)));
+        assert!(playback.advance(start + ms(100)));
+        assert_eq!(playback.frame(), 1);
+        assert!(!playback.looped());
+        assert!(playback.advance(start + ms(160)));
+        assert_eq!(playback.frame(), 0);
+        assert!(playback.looped());
+    }
+
+    #[test]
+    fn test_playback_still_image() {
+        let start = Instant::now();
+        let mut playback = Playback::new(Path::new("a.png"), vec![], start);
+        assert_eq!(playback.remaining(start), None);
+        assert!(!playback.advance(start + ms(1000)));
+        assert!(playback.looped());
+        assert_eq!(playback.frames(), 1);
+    }
+
+    #[test]
+    fn test_playback_control() {
+        let start = Instant::now();
+        let delays = vec![ms(100), ms(100), ms(100)];
+        let mut playback = Playback::new(Path::new("a.gif"), delays, start);
+        playback.control(AnimationControl::Prev, start);
+        assert_eq!(playback.frame(), 2);
+        assert!(playback.is_paused());
+        // paused animations do not hold up pageant mode
+        assert!(playback.looped());
+        assert_eq!(playback.remaining(start + ms(500)), None);
+        playback.control(AnimationControl::Next, start);
+        assert_eq!(playback.frame(), 0);
+        playback.control(AnimationControl::Toggle, start + ms(500));
+        assert!(!playback.is_paused());
+        assert_eq!(playback.remaining(start + ms(550)), Some(ms(50)));
+    }
+}
@@ -2,8 +2,11 @@
 #[derive(Debug, Clone)]
 pub struct Cursor<T> {
     items: Vec<T>,
-    /// current index position. it will be None until next() is called
+    /// index of the item `next()` returns. it will be None until the
+    /// cursor is first moved
     index: Option<usize>,
+    /// index of the item last returned
+    current: Option<usize>,
     len: usize,
 }
 
@@ -12,7 +15,12 @@ impl<T: std::clone::Clone> Cursor<T> {
     pub fn new(items: Vec<T>) -> Self {
         let index = None;
         let len = items.len();
-        Self { items, index, len }
+        Self {
+            items,
+            index,
+            current: None,
+            len,
+        }
     }
     /// get current index then advance
     pub fn next(&mut self) -> Option<T> {
@@ -23,40 +31,148 @@ impl<T: std::clone::Clone> Cursor<T> {
         // get the first image and set the index for the next call to 1
         let item = if let Some(path) = self.items.get(index) {
             self.index = Some(index + 1);
+            self.current = Some(index);
             Some(path)
         } else {
             self.index = Some(1);
+            self.current = Some(0);
             self.items.first()
         };
         item.cloned()
     }
     /// get previous
     pub fn prev(&mut self) -> Option<T> {
-        // if not None use the index, else set it to 0
-        let mut index = self.index.map_or_else(|| 0, |i| i);
-        let item = if index == 0 {
-            index = self.len - 1;
-            self.items.last()
-        } else {
-            index -= 1;
-            self.items.get(index)
+        if self.items.is_empty() {
+            return None;
+        }
+        // the item before the current one, or before the one `next()`
+        // would return when there is no current item
+        let after = match self.current {
+            Some(current) => current,
+            None => self.index.unwrap_or(0),
         };
-        self.index = Some(index);
-        item.cloned()
+        let index = after.checked_sub(1).unwrap_or(self.len - 1);
+        let index = index.min(self.len - 1);
+        // as if `next()` had just returned the item
+        self.index = Some(index + 1);
+        self.current = Some(index);
+        self.items.get(index).cloned()
+    }
+    /// Index of the item last returned
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// All items, in order
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+    /// Move the cursor onto the item at `index` and return it
+    pub fn goto(&mut self, index: usize) -> Option<T> {
+        let item = self.items.get(index)?;
+        // as if `next()` had just returned the item
+        self.index = Some(index + 1);
+        self.current = Some(index);
+        Some(item.clone())
     }
     /// check if Vec is empty
     pub fn is_empty(&self) -> bool {
         self.items.is_empty()
     }
-    /// remove
-    pub fn remove(&mut self) -> Option<T> {
+    /// Insert `item` in the position given by `compare`, or find it if
+    /// it is already present, and move the cursor onto it.
+    pub fn insert_by<F>(&mut self, item: T, mut compare: F)
+    where
+        T: PartialEq,
+        F: FnMut(&T, &T) -> std::cmp::Ordering,
+    {
+        let index = match self.items.iter().position(|x| x == &item) {
+            Some(index) => index,
+            None => {
+                let index = self.items.partition_point(|x| compare(x, &item).is_lt());
+                self.items.insert(index, item);
+                self.len += 1;
+                index
+            }
+        };
+        // as if `next()` had just returned the item
+        self.index = Some(index + 1);
+        self.current = Some(index);
+    }
+    /// Insert `item` in the position given by `compare` without moving
+    /// the cursor off the current item. Returns `false` if the item is
+    /// already present.
+    pub fn insert_sorted_by<F>(&mut self, item: T, mut compare: F) -> bool
+    where
+        T: PartialEq,
+        F: FnMut(&T, &T) -> std::cmp::Ordering,
+    {
+        if self.items.contains(&item) {
+            return false;
+        }
+        let at = self.items.partition_point(|x| compare(x, &item).is_lt());
+        self.items.insert(at, item);
+        self.len += 1;
+        // shift whatever comes after the insertion point
         if let Some(index) = self.index {
-            self.len -= 1;
-            let p = self.items.remove(index);
-            Some(p)
-        } else {
-            None
+            if at < index || self.current.is_some_and(|c| at <= c) {
+                self.index = Some(index + 1);
+            }
+        }
+        if let Some(current) = self.current {
+            if at <= current {
+                self.current = Some(current + 1);
+            }
+        }
+        true
+    }
+    /// Remove the item at `at` without moving the cursor off the current
+    /// item. When the current item itself is removed, `next()` returns
+    /// the item that followed it.
+    pub fn remove_at(&mut self, at: usize) -> Option<T> {
+        if at >= self.items.len() {
+            return None;
+        }
+        let item = self.items.remove(at);
+        self.len -= 1;
+        self.current = match self.current {
+            Some(current) if current == at => None,
+            Some(current) if at < current => Some(current - 1),
+            current => current,
+        };
+        if let Some(index) = self.index {
+            if at < index {
+                self.index = Some(index - 1);
+            }
         }
+        Some(item)
+    }
+    /// Replace all items, keeping the cursor on `current` if present.
+    pub fn replace(&mut self, items: Vec<T>, current: Option<&T>)
+    where
+        T: PartialEq,
+    {
+        let found = current.and_then(|c| items.iter().position(|x| x == c));
+        self.len = items.len();
+        self.items = items;
+        match found {
+            Some(index) => {
+                self.goto(index);
+            }
+            None => {
+                // fall back to the same position
+                let index = self.current.unwrap_or(0).min(self.len.saturating_sub(1));
+                self.index = Some(index);
+                self.current = None;
+            }
+        }
+    }
+    /// Remove the current item. See [`Self::remove_at`].
+    pub fn remove(&mut self) -> Option<T> {
+        self.remove_at(self.current?)
     }
 }
 
@@ -87,4 +203,91 @@ mod tests {
         assert_eq!(v.prev(), Some(2));
         Ok(())
     }
+    #[test]
+    fn test_cursor_change_direction() -> Result<()> {
+        let v = vec![1, 2, 3];
+        let mut v = Cursor::new(v);
+        assert_eq!(v.next(), Some(1));
+        assert_eq!(v.next(), Some(2));
+        assert_eq!(v.prev(), Some(1));
+        assert_eq!(v.next(), Some(2));
+        assert_eq!(v.remove(), Some(2));
+        assert_eq!(v.prev(), Some(1));
+        assert_eq!(v.remove(), Some(1));
+        assert_eq!(v.prev(), Some(3));
+        assert_eq!(v.remove(), Some(3));
+        assert_eq!(v.remove(), None);
+        assert_eq!(v.prev(), None);
+        assert_eq!(v.next(), None);
+        Ok(())
+    }
+    #[test]
+    fn test_cursor_goto() -> Result<()> {
+        let v = vec![1, 2, 3];
+        let mut v = Cursor::new(v);
+        assert_eq!(v.current(), None);
+        assert_eq!(v.goto(1), Some(2));
+        assert_eq!(v.current(), Some(1));
+        assert_eq!(v.next(), Some(3));
+        assert_eq!(v.current(), Some(2));
+        assert_eq!(v.goto(3), None);
+        assert_eq!(v.current(), Some(2));
+        Ok(())
+    }
+    #[test]
+    fn test_cursor_insert_by() -> Result<()> {
+        let v = vec![1, 3, 5];
+        let mut v = Cursor::new(v);
+        v.insert_by(4, |a, b| a.cmp(b));
+        assert_eq!(v.next(), Some(5));
+        v.insert_by(3, |a, b| a.cmp(b));
+        assert_eq!(v.next(), Some(4));
+        assert_eq!(v.next(), Some(5));
+        assert_eq!(v.next(), Some(1));
+        Ok(())
+    }
+    #[test]
+    fn test_cursor_insert_sorted_keeps_position() -> Result<()> {
+        let v = vec![2, 4, 6];
+        let mut v = Cursor::new(v);
+        assert_eq!(v.next(), Some(2));
+        assert_eq!(v.next(), Some(4));
+        assert!(v.insert_sorted_by(1, |a, b| a.cmp(b)));
+        assert!(v.insert_sorted_by(5, |a, b| a.cmp(b)));
+        assert!(!v.insert_sorted_by(4, |a, b| a.cmp(b)));
+        assert_eq!(v.current(), Some(2));
+        assert_eq!(v.next(), Some(5));
+        v.insert_sorted_by(3, |a, b| a.cmp(b));
+        assert_eq!(v.current(), Some(4));
+        assert_eq!(v.next(), Some(6));
+        Ok(())
+    }
+    #[test]
+    fn test_cursor_remove_at() -> Result<()> {
+        let v = vec![1, 2, 3, 4];
+        let mut v = Cursor::new(v);
+        assert_eq!(v.next(), Some(1));
+        assert_eq!(v.next(), Some(2));
+        assert_eq!(v.remove_at(0), Some(1));
+        assert_eq!(v.current(), Some(0));
+        assert_eq!(v.next(), Some(3));
+        // removing the current item moves on to its successor
+        assert_eq!(v.remove_at(1), Some(3));
+        assert_eq!(v.current(), None);
+        assert_eq!(v.next(), Some(4));
+        assert_eq!(v.remove_at(5), None);
+        Ok(())
+    }
+    #[test]
+    fn test_cursor_replace() -> Result<()> {
+        let v = vec![1, 2, 3];
+        let mut v = Cursor::new(v);
+        v.goto(1);
+        v.replace(vec![0, 1, 2, 3], Some(&2));
+        assert_eq!(v.current(), Some(2));
+        assert_eq!(v.next(), Some(3));
+        v.replace(vec![5, 6], Some(&3));
+        assert_eq!(v.next(), Some(6));
+        Ok(())
+    }
 }
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::prefetch::Decoded;
+use super::WindowState;
+use crate::window::WindowCommand;
+
+/// Where the window control loop shows its images. [`SdlWindow`] draws
+/// them on screen, [`Headless`] only records what would have been shown.
+///
+/// [`SdlWindow`]: super::SdlWindow
+/// [`Headless`]: super::Headless
+pub trait Display {
+    /// Draw `frame` of `image`, decoded from `path`, rotated, scaled,
+    /// zoomed and panned as set in `state`. The image is decoded again,
+    /// into another `Arc`, once the file changes.
+    fn show(
+        &mut self,
+        path: &Path,
+        image: &Arc<Decoded>,
+        frame: usize,
+        state: &WindowState,
+    ) -> Result<()>;
+    /// Show no image, as none is left in the collection
+    fn clear(&mut self) -> Result<()>;
+    /// Apply the parts of `state` which belong to the window rather than
+    /// the image, i.e. its title and fullscreen mode
+    fn apply(&mut self, state: &WindowState) -> Result<()>;
+    /// Wait until something happens on the display and return what did.
+    /// Gives up once `timeout` passes, if given, and returns early, maybe
+    /// without events, when woken up by a [`Display::waker`].
+    fn wait_events(&mut self, timeout: Option<Duration>) -> Vec<LocalEvent>;
+    /// A way for other threads to wake up [`Display::wait_events`]
+    fn waker(&self) -> Waker;
+}
+
+/// Wakes up a display waiting for events, e.g. when a command arrives
+pub type Waker = Arc<dyn Fn() + Send + Sync>;
+
+/// Something which happened on the display itself rather than being sent
+/// by a client
+#[derive(Debug)]
+pub enum LocalEvent {
+    /// The window was closed, the control loop stops
+    Quit,
+    /// The window size changed, the image has to be laid out again
+    Resized,
+    /// A key or mouse button bound to a command was used
+    Command(WindowCommand),
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use sdl2::rect::Rect;
+use sdl2::video::FullscreenType;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::display::{Display, LocalEvent, Waker};
+use super::prefetch::Decoded;
+use super::{layout, WindowState};
+
+/// Number of frames and states a [`Recording`] keeps, so a headless
+/// server running for a long time does not grow without bounds
+const HISTORY: usize = 1000;
+
+/// A display without a window. Nothing is drawn, instead the images which
+/// would have been shown and the window states are recorded, so the
+/// server can run in tests and on machines without a screen.
+#[derive(Debug)]
+pub struct Headless {
+    /// size of the window the images are laid out in
+    size: (u32, u32),
+    recording: Arc<Mutex<Recording>>,
+    /// a message is sent each time the display is woken up
+    wake_tx: Sender<()>,
+    wake_rx: Receiver<()>,
+}
+
+/// What a [`Headless`] display would have shown, oldest first
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub frames: VecDeque<Rendered>,
+    pub states: VecDeque<Applied>,
+    /// times the display was cleared as no image was left
+    pub cleared: usize,
+}
+
+/// An image as it would have been drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rendered {
+    pub path: PathBuf,
+    /// size of the decoded image
+    pub width: u32,
+    pub height: u32,
+    /// frame of an animated image
+    pub frame: usize,
+    /// where the image would have been drawn, before rotating it
+    pub rect: Rect,
+    /// clockwise, in degrees
+    pub rotation: f64,
+}
+
+/// Window settings as they would have been applied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Applied {
+    pub title: String,
+    pub fullscreen: bool,
+}
+
+impl Headless {
+    /// A display laying out images as in a window of `size`
+    pub fn new(size: (u32, u32)) -> Self {
+        let (wake_tx, wake_rx) = channel();
+        Self {
+            size,
+            recording: Arc::new(Mutex::new(Recording::default())),
+            wake_tx,
+            wake_rx,
+        }
+    }
+    /// What has been shown so far. The recording keeps being updated once
+    /// the display is handed to the window control loop.
+    pub fn recording(&self) -> Arc<Mutex<Recording>> {
+        self.recording.clone()
+    }
+}
+
+impl Display for Headless {
+    fn show(
+        &mut self,
+        path: &Path,
+        image: &Arc<Decoded>,
+        frame: usize,
+        state: &WindowState,
+    ) -> Result<()> {
+        let rect = layout(
+            (image.width, image.height),
+            self.size,
+            state.rotation() as i64,
+            state.scale(),
+            state.zoom(),
+            state.pan(),
+        );
+        let frame = Rendered {
+            path: path.to_path_buf(),
+            width: image.width,
+            height: image.height,
+            frame,
+            rect,
+            rotation: (state.rotation() * 90.0).rem_euclid(360.0),
+        };
+        record(&mut self.recording.lock().unwrap().frames, frame);
+        Ok(())
+    }
+    fn clear(&mut self) -> Result<()> {
+        self.recording.lock().unwrap().cleared += 1;
+        Ok(())
+    }
+    fn apply(&mut self, state: &WindowState) -> Result<()> {
+        let applied = Applied {
+            title: state.title()?.to_string(),
+            fullscreen: state.fullscreen() != FullscreenType::Off,
+        };
+        record(&mut self.recording.lock().unwrap().states, applied);
+        Ok(())
+    }
+    /// Nothing happens without a window, so this only waits to be woken
+    fn wait_events(&mut self, timeout: Option<Duration>) -> Vec<LocalEvent> {
+        // the display holds a sender itself, so the channel stays open
+        let _ = match timeout {
+            Some(timeout) => self.wake_rx.recv_timeout(timeout).ok(),
+            None => self.wake_rx.recv().ok(),
+        };
+        // one wake up is enough for everything that happened meanwhile
+        while self.wake_rx.try_recv().is_ok() {}
+        vec![]
+    }
+    fn waker(&self) -> Waker {
+        let wake_tx = self.wake_tx.clone();
+        Arc::new(move || {
+            let _ = wake_tx.send(());
+        })
+    }
+}
+
+fn record<T>(history: &mut VecDeque<T>, entry: T) {
+    if history.len() == HISTORY {
+        history.pop_front();
+    }
+    history.push_back(entry);
+}
@@ -0,0 +1,128 @@
+use sdl2::rect::Rect;
+
+use crate::window::ScaleMode;
+
+/// Compute the destination rect of an `image` sized texture inside a
+/// `window` sized canvas.
+///
+/// `rotation` counts quarter turns. The returned rect is the unrotated
+/// one expected by `copy_ex`, which rotates around its center, so for
+/// quarter and three quarter turns the bounding dimensions are swapped
+/// before scaling.
+///
+/// `zoom` multiplies the scale given by `mode` and `pan` is the point of
+/// the rotated image, as fractions of its width and height, placed at
+/// the center of the window.
+pub fn layout(
+    image: (u32, u32),
+    window: (u32, u32),
+    rotation: i64,
+    mode: ScaleMode,
+    zoom: f64,
+    pan: (f64, f64),
+) -> Rect {
+    let (iw, ih) = (image.0.max(1) as f64, image.1.max(1) as f64);
+    let (ww, wh) = (window.0 as f64, window.1 as f64);
+
+    // dimensions of the image as it appears on screen
+    let (bw, bh) = if rotation.rem_euclid(2) == 1 {
+        (ih, iw)
+    } else {
+        (iw, ih)
+    };
+
+    let scale = match mode {
+        ScaleMode::Fit => (ww / bw).min(wh / bh),
+        ScaleMode::Fill => (ww / bw).max(wh / bh),
+        ScaleMode::Actual => 1.0,
+        ScaleMode::FitWidth => ww / bw,
+    } * zoom;
+
+    // center of the image on screen, moved away from the window center
+    // so the panned point ends up there
+    let cx = ww / 2.0 + (0.5 - pan.0) * bw * scale;
+    let cy = wh / 2.0 + (0.5 - pan.1) * bh * scale;
+
+    let (dw, dh) = (iw * scale, ih * scale);
+    let x = cx - dw / 2.0;
+    let y = cy - dh / 2.0;
+
+    Rect::new(
+        x.round() as i32,
+        y.round() as i32,
+        dw.round().max(1.0) as u32,
+        dh.round().max(1.0) as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: (f64, f64) = (0.5, 0.5);
+
+    #[test]
+    fn test_layout_fit() {
+        // wide image is letterboxed top and bottom
+        let r = layout((400, 100), (800, 600), 0, ScaleMode::Fit, 1.0, CENTER);
+        assert_eq!(r, Rect::new(0, 200, 800, 200));
+        // tall image is letterboxed left and right
+        let r = layout((100, 300), (800, 600), 0, ScaleMode::Fit, 1.0, CENTER);
+        assert_eq!(r, Rect::new(300, 0, 200, 600));
+    }
+
+    #[test]
+    fn test_layout_fill() {
+        let r = layout((400, 100), (800, 600), 0, ScaleMode::Fill, 1.0, CENTER);
+        assert_eq!(r, Rect::new(-800, 0, 2400, 600));
+    }
+
+    #[test]
+    fn test_layout_actual() {
+        let r = layout((400, 100), (800, 600), 0, ScaleMode::Actual, 1.0, CENTER);
+        assert_eq!(r, Rect::new(200, 250, 400, 100));
+    }
+
+    #[test]
+    fn test_layout_fit_width() {
+        let r = layout((100, 300), (800, 600), 0, ScaleMode::FitWidth, 1.0, CENTER);
+        assert_eq!(r, Rect::new(0, -900, 800, 2400));
+    }
+
+    #[test]
+    fn test_layout_rotated() {
+        // a quarter turn makes the wide image tall, so height is the
+        // limiting dimension: 400 * 1.5 = 600 once rotated
+        let r = layout((400, 100), (800, 600), 1, ScaleMode::Fit, 1.0, CENTER);
+        assert_eq!(r, Rect::new(100, 225, 600, 150));
+        // half turns keep the bounding box
+        let r = layout((400, 100), (800, 600), 2, ScaleMode::Fit, 1.0, CENTER);
+        assert_eq!(r, Rect::new(0, 200, 800, 200));
+        let r = layout((400, 100), (800, 600), -1, ScaleMode::Fit, 1.0, CENTER);
+        assert_eq!(r, Rect::new(100, 225, 600, 150));
+    }
+
+    #[test]
+    fn test_layout_zoom() {
+        let r = layout((400, 100), (800, 600), 0, ScaleMode::Fit, 2.0, CENTER);
+        assert_eq!(r, Rect::new(-400, 100, 1600, 400));
+    }
+
+    #[test]
+    fn test_layout_pan() {
+        // left edge of the zoomed image at the window center
+        let r = layout((400, 100), (800, 600), 0, ScaleMode::Fit, 2.0, (0.0, 0.5));
+        assert_eq!(r, Rect::new(400, 100, 1600, 400));
+        // bottom right corner at the window center
+        let r = layout((400, 100), (800, 600), 0, ScaleMode::Fit, 2.0, (1.0, 1.0));
+        assert_eq!(r, Rect::new(-1200, -100, 1600, 400));
+    }
+
+    #[test]
+    fn test_layout_pan_rotated() {
+        // pan is relative to the image as shown, after rotation: the
+        // top of the rotated 300x1200 box is at the window center
+        let r = layout((400, 100), (800, 600), 1, ScaleMode::Fit, 2.0, (0.5, 0.0));
+        assert_eq!(r, Rect::new(-200, 750, 1200, 300));
+    }
+}
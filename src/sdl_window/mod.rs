@@ -3,9 +3,29 @@ mod navigator;
 mod cursor;
 
 mod window;
-pub(crate) use window::SdlWindow;
+pub(crate) use window::Window;
+
+mod display;
+pub use display::{Display, LocalEvent};
+
+mod sdl;
+pub use sdl::SdlWindow;
+
+mod headless;
+pub use headless::{Applied, Headless, Recording, Rendered};
 
 mod state;
 use state::WindowState;
 
 mod pageant;
+
+mod animation;
+
+mod prefetch;
+
+mod sort;
+
+mod sniff;
+
+mod layout;
+use layout::layout;
@@ -1,102 +1,570 @@
 use anyhow::anyhow;
 use anyhow::Result;
-use sdl2::image::LoadSurface;
-use sdl2::surface::Surface;
+use tracing::debug;
 use walkdir::WalkDir;
 
 use super::cursor::Cursor;
+use super::sniff::sniff;
+use super::sort::{compare, sort};
+use crate::glob::{glob_match, is_glob};
+use crate::window::{FileEvent, Filter, Listing, Position, Query, SortOrder, Target};
 use std::path::Path;
 use std::path::PathBuf;
 
+/// Outcome of moving to the next or previous image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// the cursor moved onto another supported image
+    Found(PathBuf),
+    /// the current image is the only supported one left
+    OnlyOne,
+    /// no supported image is left in the collection
+    Exhausted,
+}
+
+/// Files found by [`Navigator::scan`], not yet part of the collection
+#[derive(Debug)]
+pub struct Scan {
+    filter: Filter,
+    paths: Vec<PathBuf>,
+    unsupported: Vec<PathBuf>,
+}
+
 /// Navigator holds the list of images and methods to move through
 /// them. It wraps cursor to provide a facade for simplifying the
 /// cursor API. `image` holds the path of the file currently under
-/// the cursor.
+/// the cursor, or an empty path once no image is left.
 #[derive(Debug, Clone)]
 pub struct Navigator {
     cursor: Cursor<PathBuf>,
+    /// directory the images were imported from
+    root: PathBuf,
+    /// order of the images in the cursor
+    order: SortOrder,
+    /// which files are part of the collection
+    filter: Filter,
+    /// files accepted by the filter which are not supported images
+    unsupported: Vec<PathBuf>,
     pub image: PathBuf,
 }
 
 impl Navigator {
     /// initialized the cursor and checks does a basic check that
     /// cursor holds at least one path.
-    pub fn new(path: &Path) -> Result<Self> {
-        let mut cursor = Navigator::import_files(path)?;
+    pub fn new(path: &Path, order: SortOrder, filter: Filter) -> Result<Self> {
+        let (mut cursor, unsupported) = Navigator::import_files(path, order, &filter)?;
         let image = cursor.next().ok_or(anyhow!("no image found"))?;
-        let n = Self { cursor, image };
+        let root = path.to_path_buf();
+        let n = Self {
+            cursor,
+            root,
+            order,
+            filter,
+            unsupported,
+            image,
+        };
         Ok(n)
     }
-    /// Advance the cursor and return current. Test that path is a
-    /// supported image by loading it in a throw away surface. If it
-    /// is not supported, and cursor is not empty, call again. When we
-    /// encounter unsupported files, they are removed from the
-    /// cursor. Since it is possible that we are opperting on a collection
-    /// of all unsupported files, removing them will eventually result in an
-    /// empty collection and return None.
-    pub fn next(&mut self) -> Option<PathBuf> {
-        let previous = &self.image;
-        let path = self.cursor.next()?;
-        // if cursor is empty, return None
-        if self.cursor.is_empty() {
-            return None;
-        }
-        // if file is supported and cursor has moved return path
-        let maybe_image = Surface::from_file(&path);
-        if maybe_image.is_ok() && &path != previous {
-            self.image = path.to_path_buf();
-            return Some(path);
-        }
-        if maybe_image.is_err() {
-            self.cursor.remove();
-        }
-        // if above conditions are not met, call again
-        self.next()
-    }
-    /// Opposite of next. See [`Self.next()`].
-    pub fn prev(&mut self) -> Option<PathBuf> {
-        let previous = &self.image;
-        let path = self.cursor.prev()?;
-        // if cursor is empty, return None
-        if self.cursor.is_empty() {
-            return None;
-        }
-        let maybe_image = Surface::from_file(&path);
-        // if file is supported and cursor has moved return path
-        if maybe_image.is_ok() && &path != previous {
-            self.image = path.to_path_buf();
-            return Some(path);
-        }
-        // remove unsupported files from collection
-        if maybe_image.is_err() {
-            self.cursor.remove();
-        }
-        // if above conditions are not met, call again
-        self.prev()
+    /// Advance the cursor to the next supported image. `check` tests that
+    /// the path is still a supported image, as it may have changed since
+    /// the directory was scanned. Unsupported files are removed from the
+    /// cursor and listed as unsupported, so a collection of only
+    /// unsupported files ends up empty and [`Outcome::Exhausted`] is
+    /// returned, leaving `image` empty.
+    pub fn next(&mut self, check: impl FnMut(&Path) -> Result<()>) -> Outcome {
+        self.step(Cursor::next, check)
+    }
+    /// Opposite of next. See [`Self::next()`].
+    pub fn prev(&mut self, check: impl FnMut(&Path) -> Result<()>) -> Outcome {
+        self.step(Cursor::prev, check)
+    }
+    /// Move the cursor with `step` until it lands on a supported image
+    /// other than the current one.
+    fn step(
+        &mut self,
+        step: fn(&mut Cursor<PathBuf>) -> Option<PathBuf>,
+        mut check: impl FnMut(&Path) -> Result<()>,
+    ) -> Outcome {
+        // every attempt either returns or removes a file, so going round
+        // the whole collection once is enough
+        for _ in 0..=self.cursor.len() {
+            let path = match step(&mut self.cursor) {
+                Some(path) => path,
+                None => break,
+            };
+            match check(&path) {
+                // back at the current image after skipping all the others
+                Ok(()) if path == self.image => return Outcome::OnlyOne,
+                Ok(()) => {
+                    self.image = path.clone();
+                    return Outcome::Found(path);
+                }
+                // remove unsupported files from collection
+                Err(e) => {
+                    debug!("unsupported image {}: {}", path.display(), e);
+                    self.cursor.remove();
+                    self.unsupported.push(path);
+                }
+            }
+        }
+        self.image = PathBuf::new();
+        Outcome::Exhausted
+    }
+    /// Move the current image to the unsupported files, e.g. because it
+    /// could not be decoded, leaving `image` empty. [`Self::next()`]
+    /// continues with the image which followed it.
+    pub fn discard(&mut self) {
+        let image = std::mem::take(&mut self.image);
+        if let Some(index) = self.cursor.items().iter().position(|p| p == &image) {
+            self.cursor.remove_at(index);
+        }
+        if !self.unsupported.contains(&image) {
+            self.unsupported.push(image);
+        }
+    }
+    /// Check if an image is under the cursor
+    pub fn has_image(&self) -> bool {
+        !self.image.as_os_str().is_empty()
+    }
+    /// Up to `n` images on each side of the current one, nearest first
+    pub fn neighbours(&self, n: usize) -> Vec<PathBuf> {
+        let items = self.cursor.items();
+        let len = items.len();
+        let current = match self.cursor.current() {
+            Some(current) => current,
+            None => return vec![],
+        };
+        let mut paths: Vec<PathBuf> = vec![];
+        for k in 1..=n.min(len / 2) {
+            for index in [(current + k) % len, (current + len - k) % len] {
+                if !paths.contains(&items[index]) {
+                    paths.push(items[index].clone());
+                }
+            }
+        }
+        paths
+    }
+    /// Move the cursor to `target`. Fails if the target does not exist
+    /// or is not a supported image, in which case the cursor is left
+    /// where it was.
+    pub fn goto(
+        &mut self,
+        target: &Target,
+        mut check: impl FnMut(&Path) -> Result<()>,
+    ) -> Result<Position> {
+        let len = self.cursor.len();
+        if len == 0 {
+            return Err(anyhow!("collection is empty"));
+        }
+        let items = self.cursor.items();
+        let index = match target {
+            Target::Index(i) => Some(*i).filter(|i| *i < len),
+            Target::Offset(n) => {
+                let current = self.cursor.current().unwrap_or(0) as i64;
+                Some((current + n).rem_euclid(len as i64) as usize)
+            }
+            Target::Path(path) => {
+                let path = self.root.join(path);
+                items.iter().position(|p| p == &path)
+            }
+            Target::Name(name) => items
+                .iter()
+                .position(|p| p.file_name().is_some_and(|n| n == name.as_str())),
+        };
+        let index = index.ok_or_else(|| anyhow!("no image matching '{}'", target))?;
+        let path = items[index].clone();
+        if let Err(e) = check(&path) {
+            return Err(anyhow!("unsupported image {}: {}", path.display(), e));
+        }
+        self.cursor.goto(index);
+        self.image = path;
+        Ok(self.position())
+    }
+    /// Location of the current image
+    pub fn position(&self) -> Position {
+        let path = self.image.strip_prefix(&self.root).unwrap_or(&self.image);
+        Position {
+            index: self.cursor.current().unwrap_or(0),
+            total: self.cursor.len(),
+            path: path.to_path_buf(),
+        }
+    }
+    /// Entries matching `query`, paged by its offset and limit. Patterns
+    /// containing glob characters are matched as globs, others as case
+    /// insensitive substrings of the relative path.
+    pub fn list(&self, query: &Query) -> Listing {
+        let pattern = query
+            .pattern
+            .as_deref()
+            .map(|p| (p, is_glob(p), p.to_lowercase()));
+        let matching = self
+            .cursor
+            .items()
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (i, path.strip_prefix(&self.root).unwrap_or(path)))
+            .filter(|(_, path)| match &pattern {
+                None => true,
+                Some((glob, true, _)) => glob_match(glob, &path.to_string_lossy()),
+                Some((_, false, needle)) => path.to_string_lossy().to_lowercase().contains(needle),
+            });
+        let mut total = 0;
+        let mut entries = vec![];
+        for (i, path) in matching {
+            if total >= query.offset && entries.len() < query.limit {
+                entries.push((i, path.to_path_buf()));
+            }
+            total += 1;
+        }
+        Listing {
+            current: self.cursor.current().unwrap_or(0),
+            total,
+            entries,
+        }
+    }
+    /// Make `path` the current image, adding it to the collection
+    /// if it is not already part of it.
+    pub fn open(&mut self, path: PathBuf) {
+        let order = self.order;
+        self.cursor
+            .insert_by(path.clone(), |a, b| compare(a, b, order));
+        self.image = path;
+    }
+    /// Current order of the collection
+    pub fn order(&self) -> SortOrder {
+        self.order
+    }
+    /// Sort the collection in `order`, keeping the cursor on the current
+    /// image.
+    pub fn set_order(&mut self, order: SortOrder) -> Position {
+        let mut paths = self.cursor.items().to_vec();
+        sort(&mut paths, order);
+        self.cursor.replace(paths, Some(&self.image));
+        self.order = order;
+        self.position()
+    }
+    /// Check if the collection is empty
+    pub fn is_empty(&self) -> bool {
+        self.cursor.is_empty()
+    }
+    /// Apply a change to the image directory, keeping the cursor on the
+    /// current image. Returns `true` if the current image is gone and
+    /// another one must be displayed.
+    pub fn apply(&mut self, event: &FileEvent) -> bool {
+        match event {
+            FileEvent::Created(path) => {
+                self.insert(path);
+                false
+            }
+            FileEvent::Removed(path) => self.remove(path),
+            FileEvent::Renamed(from, to) => {
+                let moved = self.image.strip_prefix(from).map(|rest| to.join(rest)).ok();
+                self.remove(from);
+                self.insert(to);
+                // follow the current image to its new name
+                let found = moved.as_ref().and_then(|moved| {
+                    let index = self.cursor.items().iter().position(|p| p == moved)?;
+                    self.cursor.goto(index)
+                });
+                match found {
+                    Some(image) => {
+                        self.image = image;
+                        false
+                    }
+                    None => moved.is_some(),
+                }
+            }
+        }
+    }
+    /// Add `path`, or every file under it if it is a directory, as far
+    /// as the filter accepts them.
+    fn insert(&mut self, path: &Path) {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => return,
+        };
+        // the directories above `path` must be accepted too
+        if let Some(parent) = relative.parent() {
+            let mut dir = PathBuf::new();
+            for component in parent.components() {
+                dir.push(component);
+                if !self.filter.accepts_dir(&dir) {
+                    return;
+                }
+            }
+        }
+        let (paths, unsupported) = match path.metadata() {
+            Ok(m) if m.is_dir() => {
+                if !self.filter.accepts_dir(relative) {
+                    return;
+                }
+                Self::find_files(&self.root, path, &self.filter)
+            }
+            Ok(m) if self.filter.accepts_file(relative, m.len()) => match sniff(path) {
+                Some(_) => (vec![path.to_path_buf()], vec![]),
+                None => (vec![], vec![path.to_path_buf()]),
+            },
+            _ => return,
+        };
+        for path in unsupported {
+            if !self.unsupported.contains(&path) {
+                self.unsupported.push(path);
+            }
+        }
+        let order = self.order;
+        for path in paths {
+            // shuffled collections get new images at an arbitrary place
+            self.cursor
+                .insert_sorted_by(path, |a, b| compare(a, b, order));
+        }
+    }
+    /// Remove `path` and everything under it. Returns `true` if the current
+    /// image was removed.
+    fn remove(&mut self, path: &Path) -> bool {
+        self.unsupported.retain(|p| !p.starts_with(path));
+        let mut removed = false;
+        for index in (0..self.cursor.len()).rev() {
+            if self.cursor.items()[index].starts_with(path) {
+                self.cursor.remove_at(index);
+                removed = true;
+            }
+        }
+        removed && self.image.starts_with(path)
+    }
+    /// Rescan the image directory. The cursor stays on the current image
+    /// if it still exists. Returns `true` if it does not.
+    pub fn reload(&mut self) -> Result<bool> {
+        let scan = self.scan(self.filter.clone())?;
+        Ok(self.apply_scan(scan))
+    }
+    /// Change the filter and rescan. See [`Self::reload`].
+    pub fn set_filter(&mut self, filter: Filter) -> Result<bool> {
+        let scan = self.scan(filter)?;
+        Ok(self.apply_scan(scan))
+    }
+    /// Scan the image directory with `filter`, leaving the collection as it
+    /// is. Fails if no files are found.
+    pub fn scan(&self, filter: Filter) -> Result<Scan> {
+        let (paths, unsupported) = Self::find_files(&self.root, &self.root, &filter);
+        if paths.is_empty() {
+            return Err(anyhow!("no files found in image directory"));
+        }
+        Ok(Scan {
+            filter,
+            paths,
+            unsupported,
+        })
+    }
+    /// Replace the collection with the files found by `scan`. The cursor
+    /// stays on the current image if it still exists. Returns `true` if it
+    /// does not.
+    pub fn apply_scan(&mut self, scan: Scan) -> bool {
+        let Scan {
+            filter,
+            mut paths,
+            unsupported,
+        } = scan;
+        sort(&mut paths, self.order);
+        self.cursor.replace(paths, Some(&self.image));
+        self.filter = filter;
+        self.unsupported = unsupported;
+        self.cursor.current().is_none()
+    }
+    /// Current filter
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+    /// Files left out of the collection because they are not supported
+    /// images, relative to the image directory
+    pub fn unsupported(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .unsupported
+            .iter()
+            .map(|p| p.strip_prefix(&self.root).unwrap_or(p).to_path_buf())
+            .collect();
+        paths.sort();
+        paths
     }
     /// Import all the files under given dir path, performing some sanity checks.
-    pub fn import_files(path: &Path) -> Result<Cursor<PathBuf>> {
+    /// Also returns the files which are not supported images.
+    pub fn import_files(
+        path: &Path,
+        order: SortOrder,
+        filter: &Filter,
+    ) -> Result<(Cursor<PathBuf>, Vec<PathBuf>)> {
+        let (mut paths, unsupported) = Self::find_files(path, path, filter);
+        if paths.is_empty() {
+            return Err(anyhow!("no files found in image directory"));
+        }
+        sort(&mut paths, order);
+        Ok((Cursor::new(paths), unsupported))
+    }
+    /// Every file under `path` accepted by `filter`, in no particular
+    /// order, split into supported images and other files. `root` is the
+    /// image directory paths are matched relative to.
+    fn find_files(root: &Path, path: &Path, filter: &Filter) -> (Vec<PathBuf>, Vec<PathBuf>) {
         use rayon::prelude::*;
-        let mut paths = WalkDir::new(path)
+        let relative = |e: &walkdir::DirEntry| e.path().strip_prefix(root).map(Path::to_path_buf);
+        WalkDir::new(path)
+            .follow_links(filter.follow_symlinks)
             .into_iter()
+            // skip excluded directories entirely, `path` itself was checked
+            // by the caller
+            .filter_entry(|e| {
+                e.depth() == 0
+                    || !e.file_type().is_dir()
+                    || relative(e).is_ok_and(|r| filter.accepts_dir(&r))
+            })
             .par_bridge()
             // ignore i/o errors
             .filter_map(|e| e.ok())
             // filter out directories
             .filter(|x| !x.file_type().is_dir())
+            .filter(|x| {
+                let size = x.metadata().map_or(0, |m| m.len());
+                relative(x).is_ok_and(|r| filter.accepts_file(&r, size))
+            })
             .map(|x| x.into_path())
-            .collect::<Vec<PathBuf>>();
-
-        if paths.is_empty() {
-            return Err(anyhow!("no files found in image directory"));
-        }
-        paths.par_sort_unstable_by(|a, b| a.file_name().cmp(&b.file_name()));
-        Ok(Cursor::new(paths))
+            // only look at the first bytes instead of decoding every file
+            .partition(|x| sniff(x).is_some())
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    /// Directory holding a PNG header in each of `names`
+    fn fixture(test: &str, names: &[String]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("viewd-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in names {
+            fs::write(dir.join(name), PNG).unwrap();
+        }
+        dir
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    /// Reject files whose name starts with "bad"
+    fn check(path: &Path) -> Result<()> {
+        match path
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with("bad"))
+        {
+            Some(true) => Err(anyhow!("bad image")),
+            _ => Ok(()),
+        }
+    }
+
+    fn found(dir: &Path, name: &str) -> Outcome {
+        Outcome::Found(dir.join(name))
+    }
+
+    #[test]
+    fn test_next_skips_unsupported() -> Result<()> {
+        let mut files: Vec<String> = (0..2000).map(|i| format!("bad{:04}.png", i)).collect();
+        files.extend(names(&["a.png", "z.png"]));
+        let dir = fixture("skip", &files);
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        assert_eq!(n.image, dir.join("a.png"));
+        assert_eq!(n.next(check), found(&dir, "z.png"));
+        assert_eq!(n.prev(check), found(&dir, "a.png"));
+        assert_eq!(n.unsupported().len(), 2000);
+        assert_eq!(n.position().total, 2);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_only_one_image() -> Result<()> {
+        let dir = fixture("only", &names(&["a.png", "bad1.png", "bad2.png"]));
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        assert_eq!(n.next(check), Outcome::OnlyOne);
+        assert_eq!(n.prev(check), Outcome::OnlyOne);
+        assert_eq!(n.image, dir.join("a.png"));
+        assert_eq!(n.position().total, 1);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_exhausted() -> Result<()> {
+        let dir = fixture("exhausted", &names(&["a.png", "b.png"]));
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        assert_eq!(n.next(|_| Err(anyhow!("gone"))), Outcome::Exhausted);
+        assert!(n.is_empty());
+        assert!(!n.has_image());
+        assert_eq!(n.prev(check), Outcome::Exhausted);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_goto_empty() -> Result<()> {
+        let dir = fixture("goto-empty", &names(&["a.png"]));
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        assert_eq!(n.next(|_| Err(anyhow!("gone"))), Outcome::Exhausted);
+        for target in [Target::Offset(1), Target::Offset(-3), Target::Index(0)] {
+            let e = n.goto(&target, check).unwrap_err();
+            assert_eq!(e.to_string(), "collection is empty");
+        }
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_discard() -> Result<()> {
+        let dir = fixture("discard", &names(&["a.png", "b.png", "c.png"]));
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        assert_eq!(n.next(check), found(&dir, "b.png"));
+        n.discard();
+        assert!(!n.has_image());
+        assert_eq!(n.unsupported(), vec![PathBuf::from("b.png")]);
+        assert_eq!(n.next(check), found(&dir, "c.png"));
+        assert_eq!(n.position().total, 2);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_applied_later() -> Result<()> {
+        let dir = fixture("scan", &names(&["a.png", "b.png", "c.jpg"]));
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        let filter = Filter {
+            extensions: vec!["jpg".into()],
+            ..Filter::default()
+        };
+        let scan = n.scan(filter.clone())?;
+        assert_eq!(n.position().total, 3);
+        assert!(n.apply_scan(scan));
+        assert_eq!(n.position().total, 1);
+        assert_eq!(n.filter(), &filter);
+        let filter = Filter {
+            extensions: vec!["gif".into()],
+            ..filter
+        };
+        assert!(n.scan(filter).is_err());
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_change_direction() -> Result<()> {
+        let dir = fixture("direction", &names(&["a.png", "b.png", "c.png"]));
+        let mut n = Navigator::new(&dir, SortOrder::Name, Filter::default())?;
+        assert_eq!(n.next(check), found(&dir, "b.png"));
+        assert_eq!(n.prev(check), found(&dir, "a.png"));
+        assert_eq!(n.prev(check), found(&dir, "c.png"));
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
 
     #[test]
     fn test_walkdir() -> Result<()> {
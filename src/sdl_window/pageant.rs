@@ -1,12 +1,30 @@
+use std::fmt;
 use std::time::{Duration, Instant};
 
+use crate::shuffle::Rng;
+use crate::window::{Direction, Order, PageantControl};
+
+/// Where pageant mode moves next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Next,
+    Prev,
+    /// Jump to the image at this index
+    Goto(usize),
+}
+
 /// A type to represent pageant mode
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct PageantMode {
     /// milliseconds each image will remain visible
     timeout: u64,
     /// time of last update
     instant: Option<Instant>,
+    direction: Direction,
+    order: Order,
+    /// indices left to show before the shuffled order repeats
+    deck: Vec<usize>,
+    rng: Rng,
 }
 
 impl PageantMode {
@@ -14,6 +32,10 @@ impl PageantMode {
         Self {
             timeout,
             instant: None,
+            direction: Direction::default(),
+            order: Order::default(),
+            deck: vec![],
+            rng: Rng::from_time(),
         }
     }
     /// Toggle Option, self.instant is `Some`, we are in pageant mode.
@@ -25,6 +47,38 @@ impl PageantMode {
             self.instant = Some(Instant::now());
         }
     }
+    /// Apply a change requested by a client
+    pub fn control(&mut self, control: PageantControl) {
+        match control {
+            PageantControl::Start => {
+                if !self.is_active() {
+                    self.set_instant();
+                }
+            }
+            PageantControl::Stop => self.instant = None,
+            PageantControl::Toggle => self.toggle(),
+            PageantControl::Interval(ms) => self.timeout = ms,
+            PageantControl::Direction(direction) => self.direction = direction,
+            PageantControl::Order(order) => {
+                self.order = order;
+                self.deck.clear();
+            }
+        }
+    }
+    /// Returns `true` while pageant mode is on.
+    pub fn is_active(&self) -> bool {
+        self.instant.is_some()
+    }
+    /// Milliseconds each image remains visible
+    pub fn timeout(&self) -> u64 {
+        self.timeout
+    }
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+    pub fn order(&self) -> Order {
+        self.order
+    }
     pub fn set_instant(&mut self) {
         self.instant = Some(Instant::now());
     }
@@ -36,6 +90,41 @@ impl PageantMode {
             false
         }
     }
+    /// Time left until the next image is due, `None` while pageant mode
+    /// is off
+    pub fn remaining(&self) -> Option<Duration> {
+        self.instant
+            .map(|instant| Duration::from_millis(self.timeout).saturating_sub(instant.elapsed()))
+    }
+    /// Pick the image to show next in a collection of `total` images
+    /// where `current` is displayed.
+    pub fn step(&mut self, current: usize, total: usize) -> Step {
+        match (self.order, self.direction) {
+            (Order::Sequential, Direction::Forward) => Step::Next,
+            (Order::Sequential, Direction::Reverse) => Step::Prev,
+            (Order::Shuffle, _) => {
+                // images may have been removed since the deck was dealt
+                self.deck.retain(|i| *i < total && *i != current);
+                if self.deck.is_empty() {
+                    self.deck = (0..total).filter(|i| *i != current).collect();
+                    self.rng.shuffle(&mut self.deck);
+                }
+                self.deck.pop().map_or(Step::Next, Step::Goto)
+            }
+        }
+    }
+}
+
+/// Settings in the form accepted by `SET pageant`
+impl fmt::Display for PageantMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let on_off = if self.is_active() { "on" } else { "off" };
+        write!(
+            f,
+            "{} {} {} {}",
+            on_off, self.timeout, self.direction, self.order
+        )
+    }
 }
 
 #[cfg(test)]
@@ -51,4 +140,50 @@ mod tests {
         assert!(pageant.instant.is_some());
         Ok(())
     }
+
+    #[test]
+    fn control_pageant() -> Result<()> {
+        let mut pageant = PageantMode::new(1000);
+        pageant.control(PageantControl::Start);
+        pageant.control(PageantControl::Start);
+        assert!(pageant.is_active());
+        pageant.control(PageantControl::Interval(250));
+        pageant.control(PageantControl::Direction(Direction::Reverse));
+        assert_eq!(pageant.to_string(), "on 250 reverse sequential");
+        assert_eq!(pageant.step(0, 3), Step::Prev);
+        pageant.control(PageantControl::Stop);
+        assert!(!pageant.is_active());
+        Ok(())
+    }
+
+    #[test]
+    fn remaining_until_next_image() -> Result<()> {
+        let mut pageant = PageantMode::new(60_000);
+        assert_eq!(pageant.remaining(), None);
+        pageant.control(PageantControl::Start);
+        let remaining = pageant.remaining().unwrap();
+        assert!(remaining > Duration::from_secs(59) && remaining <= Duration::from_secs(60));
+        pageant.control(PageantControl::Interval(0));
+        assert_eq!(pageant.remaining(), Some(Duration::ZERO));
+        assert!(pageant.should_update());
+        Ok(())
+    }
+
+    #[test]
+    fn shuffle_shows_every_image_once() -> Result<()> {
+        let mut pageant = PageantMode::new(1000);
+        pageant.control(PageantControl::Order(Order::Shuffle));
+        let mut current = 0;
+        let mut seen = vec![current];
+        for _ in 0..4 {
+            match pageant.step(current, 5) {
+                Step::Goto(i) => current = i,
+                step => panic!("unexpected {:?}", step),
+            }
+            seen.push(current);
+        }
+        seen.sort();
+        assert_eq!(seen, vec![0, 1, 2, 3, 4]);
+        Ok(())
+    }
 }
@@ -0,0 +1,411 @@
+use anyhow::{anyhow, Result};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::AnimationDecoder;
+use sdl2::image::LoadSurface;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use super::sniff::{sniff, Format};
+
+/// Pixel format images are decoded to
+pub const FORMAT: PixelFormatEnum = PixelFormatEnum::ABGR8888;
+
+/// Frames of animations shown for less than this are shown for
+/// [`DEFAULT_DELAY`] instead, as browsers do
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest wait for an image the pool is decoding before decoding it
+/// right away instead
+const DECODE_WAIT: Duration = Duration::from_secs(5);
+
+/// An image decoded into memory. Unlike SDL surfaces it can be handed
+/// from the decoding threads to the window.
+#[derive(Debug)]
+pub struct Decoded {
+    pub width: u32,
+    pub height: u32,
+    /// bytes per row
+    pub pitch: usize,
+    /// pixels in [`FORMAT`] of each frame, only animations have more than
+    /// one
+    pub frames: Vec<Vec<u8>>,
+    /// how long each frame is shown, empty unless animated
+    pub delays: Vec<Duration>,
+}
+
+impl Decoded {
+    /// Decode the image at `path`. Fails if it is not a supported image.
+    /// Animations taking more than `budget` bytes only get their first
+    /// frame decoded.
+    pub fn load(path: &Path, budget: usize) -> Result<Decoded> {
+        match Decoded::load_animation(path, budget) {
+            Ok(Some(animation)) => return Ok(animation),
+            Ok(None) => {}
+            // SDL_image may still be able to show the first frame
+            Err(e) => debug!("could not decode animation {}: {}", path.display(), e),
+        }
+        let surface = Surface::from_file(path).map_err(|e| anyhow!(e))?;
+        let surface = surface.convert_format(FORMAT).map_err(|e| anyhow!(e))?;
+        let pixels = surface
+            .without_lock()
+            .ok_or(anyhow!("surface must be locked"))?
+            .to_vec();
+        Ok(Decoded {
+            width: surface.width(),
+            height: surface.height(),
+            pitch: surface.pitch() as usize,
+            frames: vec![pixels],
+            delays: vec![],
+        })
+    }
+    /// Decode every frame of a GIF or APNG, or only the first one if all
+    /// of them would take more than `budget` bytes. Returns `None` for
+    /// other images, which SDL_image decodes.
+    fn load_animation(path: &Path, budget: usize) -> Result<Option<Decoded>> {
+        let reader = BufReader::new(File::open(path)?);
+        let frames = match sniff(path) {
+            Some(Format::Gif) => GifDecoder::new(reader)?.into_frames(),
+            Some(Format::Png) => {
+                let decoder = PngDecoder::new(reader)?;
+                if !decoder.is_apng() {
+                    return Ok(None);
+                }
+                decoder.apng().into_frames()
+            }
+            _ => return Ok(None),
+        };
+        let mut dimensions = None;
+        let mut decoded: Vec<Vec<u8>> = vec![];
+        let mut delays = vec![];
+        let mut used = 0;
+        for frame in frames {
+            let frame = frame?;
+            let delay = Duration::from(frame.delay());
+            let buffer = frame.into_buffer();
+            dimensions.get_or_insert(buffer.dimensions());
+            let mut pixels = buffer.into_raw();
+            used += pixels.len();
+            // long or large animations are shown as still images rather
+            // than taking up all memory
+            if used > budget && !decoded.is_empty() {
+                debug!("animation {} exceeds the cache size", path.display());
+                decoded.truncate(1);
+                delays.clear();
+                break;
+            }
+            // RGBA bytes are the packed FORMAT on little endian machines
+            if cfg!(target_endian = "big") {
+                pixels.chunks_exact_mut(4).for_each(|pixel| pixel.reverse());
+            }
+            decoded.push(pixels);
+            delays.push(if delay < MIN_DELAY {
+                DEFAULT_DELAY
+            } else {
+                delay
+            });
+        }
+        let (width, height) = dimensions.ok_or_else(|| anyhow!("no frames"))?;
+        if decoded.len() == 1 {
+            delays.clear();
+        }
+        Ok(Some(Decoded {
+            width,
+            height,
+            pitch: width as usize * 4,
+            frames: decoded,
+            delays,
+        }))
+    }
+    /// Memory used by the pixels
+    fn size(&self) -> usize {
+        self.frames.iter().map(Vec::len).sum()
+    }
+}
+
+/// Decode the image at `path`, see [`Decoded::load`]. A decoder panicking
+/// on a malformed file fails the decode rather than the thread.
+fn decode(path: &Path, budget: usize) -> Result<Decoded> {
+    std::panic::catch_unwind(|| Decoded::load(path, budget))
+        .unwrap_or_else(|_| Err(anyhow!("decoder panicked on {}", path.display())))
+}
+
+/// Decoded images, evicting the least recently used ones once they take
+/// up more than `budget` bytes.
+#[derive(Debug)]
+struct Cache {
+    entries: HashMap<PathBuf, Arc<Decoded>>,
+    /// least recently used first
+    order: VecDeque<PathBuf>,
+    used: usize,
+    budget: usize,
+}
+
+impl Cache {
+    fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            used: 0,
+            budget,
+        }
+    }
+    /// Get an image, marking it as most recently used
+    fn get(&mut self, path: &Path) -> Option<Arc<Decoded>> {
+        let image = self.entries.get(path)?.clone();
+        self.touch(path);
+        Some(image)
+    }
+    fn contains(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+    /// Add an image, evicting others if needed. The image just added
+    /// is kept even if it exceeds the budget on its own.
+    fn insert(&mut self, path: PathBuf, image: Arc<Decoded>) {
+        self.remove(&path);
+        self.used += image.size();
+        self.order.push_back(path.clone());
+        self.entries.insert(path, image);
+        while self.used > self.budget && self.order.len() > 1 {
+            if let Some(oldest) = self.order.pop_front() {
+                if let Some(image) = self.entries.remove(&oldest) {
+                    self.used -= image.size();
+                }
+            }
+        }
+    }
+    fn remove(&mut self, path: &Path) {
+        if let Some(image) = self.entries.remove(path) {
+            self.used -= image.size();
+            self.order.retain(|p| p != path);
+        }
+    }
+    fn touch(&mut self, path: &Path) {
+        if let Some(i) = self.order.iter().position(|p| p == path) {
+            if let Some(path) = self.order.remove(i) {
+                self.order.push_back(path);
+            }
+        }
+    }
+}
+
+/// Finished decode of a path, with the token it was requested with
+type Decoding = (PathBuf, u64, Result<Decoded>);
+
+/// Decodes images on a pool of worker threads ahead of time so that
+/// moving to a neighbouring image does not wait for the disk and decoder.
+pub struct Prefetcher {
+    pool: rayon::ThreadPool,
+    /// Finished decodes are sent back over this channel
+    tx: Sender<Decoding>,
+    rx: Receiver<Decoding>,
+    /// Images queued on or being decoded by the pool, with the token of
+    /// the latest request. Results of earlier requests are stale.
+    pending: HashMap<PathBuf, u64>,
+    /// Token of the next request
+    token: u64,
+    cache: Cache,
+}
+
+impl Prefetcher {
+    /// Create a prefetcher decoding on `threads` workers and keeping up
+    /// to `budget` bytes of decoded images.
+    pub fn new(threads: usize, budget: usize) -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .thread_name(|i| format!("viewd-decode-{}", i))
+            .build()?;
+        let (tx, rx) = channel();
+        Ok(Self {
+            pool,
+            tx,
+            rx,
+            pending: HashMap::new(),
+            token: 0,
+            cache: Cache::new(budget),
+        })
+    }
+    /// Queue `paths` for decoding, nearest first. Images already cached or
+    /// queued are skipped.
+    pub fn request(&mut self, paths: &[PathBuf]) {
+        self.receive();
+        for path in paths {
+            if self.cache.contains(path) || self.pending.contains_key(path) {
+                continue;
+            }
+            let token = self.token;
+            self.token += 1;
+            self.pending.insert(path.clone(), token);
+            let tx = self.tx.clone();
+            let path = path.clone();
+            let budget = self.cache.budget;
+            // spawn_fifo so nearer images are decoded first
+            self.pool.spawn_fifo(move || {
+                let decoded = decode(&path, budget);
+                let _ = tx.send((path, token, decoded));
+            });
+        }
+    }
+    /// Get the decoded image at `path`, waiting for the pool if it is being
+    /// decoded, and decoding it right away otherwise.
+    pub fn load(&mut self, path: &Path) -> Result<Arc<Decoded>> {
+        self.receive();
+        if let Some(image) = self.cache.get(path) {
+            debug!("cache hit: {}", path.display());
+            return Ok(image);
+        }
+        if self.pending.contains_key(path) {
+            let deadline = Instant::now() + DECODE_WAIT;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                let (done, token, decoded) = match self.rx.recv_timeout(left) {
+                    Ok(decoding) => decoding,
+                    Err(_) => break,
+                };
+                if let Some(image) = self.store(done.clone(), token, decoded) {
+                    if done == path {
+                        return image;
+                    }
+                }
+            }
+            // the pool is busy with other images, its result is dropped
+            // once it arrives
+            self.pending.remove(path);
+        }
+        debug!("cache miss: {}", path.display());
+        let image = Arc::new(decode(path, self.cache.budget)?);
+        self.cache.insert(path.to_path_buf(), image.clone());
+        Ok(image)
+    }
+    /// Drop cached images at or under `path`, as they changed on disk
+    pub fn invalidate(&mut self, path: &Path) {
+        let stale: Vec<PathBuf> = self
+            .cache
+            .order
+            .iter()
+            .filter(|p| p.starts_with(path))
+            .cloned()
+            .collect();
+        for p in stale {
+            self.cache.remove(&p);
+        }
+        // results of decodes still running are discarded, even if the
+        // image is requested again before they arrive
+        self.pending.retain(|p, _| !p.starts_with(path));
+    }
+    /// Move finished decodes into the cache
+    fn receive(&mut self) {
+        while let Ok((path, token, decoded)) = self.rx.try_recv() {
+            self.store(path, token, decoded);
+        }
+    }
+    /// Cache a finished decode. Returns `None` if it was not requested
+    /// anymore or was requested again, e.g. because it was invalidated
+    /// meanwhile.
+    fn store(
+        &mut self,
+        path: PathBuf,
+        token: u64,
+        decoded: Result<Decoded>,
+    ) -> Option<Result<Arc<Decoded>>> {
+        if self.pending.get(&path) != Some(&token) {
+            return None;
+        }
+        self.pending.remove(&path);
+        Some(decoded.map(|image| {
+            let image = Arc::new(image);
+            self.cache.insert(path, image.clone());
+            image
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoded(size: usize) -> Decoded {
+        Decoded {
+            width: 1,
+            height: 1,
+            pitch: size,
+            frames: vec![vec![0; size]],
+            delays: vec![],
+        }
+    }
+
+    fn image(size: usize) -> Arc<Decoded> {
+        Arc::new(decoded(size))
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = Cache::new(30);
+        cache.insert("a".into(), image(10));
+        cache.insert("b".into(), image(10));
+        cache.insert("c".into(), image(10));
+        assert!(cache.get(Path::new("a")).is_some());
+        cache.insert("d".into(), image(10));
+        assert!(cache.contains(Path::new("a")));
+        assert!(!cache.contains(Path::new("b")));
+        assert_eq!(cache.used, 30);
+    }
+
+    #[test]
+    fn test_cache_keeps_oversized_image() {
+        let mut cache = Cache::new(10);
+        cache.insert("a".into(), image(5));
+        cache.insert("b".into(), image(20));
+        assert!(!cache.contains(Path::new("a")));
+        assert!(cache.contains(Path::new("b")));
+        cache.insert("b".into(), image(5));
+        assert_eq!(cache.used, 5);
+    }
+
+    #[test]
+    fn test_stale_decode_dropped() -> Result<()> {
+        let mut prefetch = Prefetcher::new(1, 100)?;
+        let path = PathBuf::from("a.png");
+        // requested, invalidated and requested again
+        prefetch.pending.insert(path.clone(), 1);
+        assert!(prefetch.store(path.clone(), 0, Ok(decoded(10))).is_none());
+        assert!(!prefetch.cache.contains(&path));
+        assert!(prefetch.store(path.clone(), 1, Ok(decoded(10))).is_some());
+        assert!(prefetch.cache.contains(&path));
+        assert!(prefetch.pending.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_animation_over_budget() -> Result<()> {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, Rgba, RgbaImage};
+
+        let path = std::env::temp_dir().join(format!("viewd-anim-{}.gif", std::process::id()));
+        let mut encoder = GifEncoder::new(File::create(&path)?);
+        for red in [0, 128, 255] {
+            let pixels = RgbaImage::from_pixel(2, 2, Rgba([red, 0, 0, 255]));
+            let delay = Delay::from_numer_denom_ms(50, 1);
+            encoder.encode_frame(Frame::from_parts(pixels, 0, 0, delay))?;
+        }
+        drop(encoder);
+
+        let animation = Decoded::load_animation(&path, 1024)?.unwrap();
+        assert_eq!(animation.frames.len(), 3);
+        assert_eq!(animation.delays, vec![Duration::from_millis(50); 3]);
+        // 2x2 frames take 16 bytes each
+        let still = Decoded::load_animation(&path, 40)?.unwrap();
+        assert_eq!(still.frames.len(), 1);
+        assert!(still.delays.is_empty());
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+use anyhow::{anyhow, Result};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::video::WindowContext;
+use sdl2::{EventPump, EventSubsystem};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::display::{Display, LocalEvent, Waker};
+use super::prefetch::{Decoded, FORMAT};
+use super::{layout, WindowState};
+use crate::window::{AnimationControl, PageantControl, Pan, WindowCommand, Zoom};
+
+/// Pixels the mouse has to move with the button held before a click
+/// becomes a drag
+const DRAG_THRESHOLD: i32 = 4;
+
+/// Shows the images in an SDL window
+pub struct SdlWindow {
+    /// Sdl Window Canvas to draw images on
+    canvas: WindowCanvas,
+    /// Creates the textures of the canvas. It lives as long as the
+    /// program so that a texture can be kept between draws.
+    texture_creator: &'static TextureCreator<WindowContext>,
+    /// The frame last shown
+    uploaded: Option<Uploaded>,
+    /// Listener to receive commands from the Window
+    event_pump: EventPump,
+    /// Used to push [`Wake`] events
+    events: EventSubsystem,
+    /// How the current image was drawn, for dragging it around
+    view: Option<View>,
+    /// Where the left mouse button was pressed, while it is held
+    press: Option<Press>,
+}
+
+/// A frame uploaded to a texture, drawn again without uploading it while
+/// only the placement changes, e.g. when zooming or panning
+struct Uploaded {
+    path: PathBuf,
+    /// the decoded image, which is replaced once the file changes
+    image: Arc<Decoded>,
+    frame: usize,
+    texture: Texture<'static>,
+}
+
+impl Uploaded {
+    /// Check if the texture holds `frame` of `image`
+    fn holds(&self, path: &Path, image: &Arc<Decoded>, frame: usize) -> bool {
+        self.path == path && self.frame == frame && Arc::ptr_eq(&self.image, image)
+    }
+}
+
+/// Placement of the image last drawn
+#[derive(Debug, Clone, Copy)]
+struct View {
+    /// destination rect before rotating, in output pixels
+    rect: Rect,
+    /// quarter turns
+    rotation: i64,
+    pan: (f64, f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    x: i32,
+    y: i32,
+    /// the mouse moved far enough to drag rather than click
+    dragging: bool,
+}
+
+/// Pushed to the event queue to wake up the control loop
+struct Wake;
+
+impl SdlWindow {
+    pub fn new(title: &str) -> Result<Self> {
+        let sdl_context = sdl2::init().map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+        let event_pump = sdl_context
+            .event_pump()
+            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+        let events = sdl_context
+            .event()
+            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+        events
+            .register_custom_event::<Wake>()
+            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+        let video_subsystem = sdl_context
+            .video()
+            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+        let window = video_subsystem
+            .window(title, 800, 600)
+            .position_centered()
+            .resizable()
+            .build()
+            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+
+        let canvas = window
+            .into_canvas()
+            .present_vsync()
+            .software()
+            .target_texture()
+            .build()
+            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
+
+        let texture_creator = Box::leak(Box::new(canvas.texture_creator()));
+
+        Ok(Self {
+            canvas,
+            texture_creator,
+            uploaded: None,
+            event_pump,
+            events,
+            view: None,
+            press: None,
+        })
+    }
+    /// Command bound to a mouse event, if any
+    fn mouse(&mut self, event: &Event) -> Option<WindowCommand> {
+        match *event {
+            Event::MouseWheel { y, direction, .. } => {
+                let y = match direction {
+                    MouseWheelDirection::Flipped => -y,
+                    _ => y,
+                };
+                match y.signum() {
+                    1 => Some(WindowCommand::Zoom(Zoom::In)),
+                    -1 => Some(WindowCommand::Zoom(Zoom::Out)),
+                    _ => None,
+                }
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                self.press = Some(Press {
+                    x,
+                    y,
+                    dragging: false,
+                });
+                None
+            }
+            Event::MouseMotion {
+                x, y, xrel, yrel, ..
+            } => {
+                let press = self.press.as_mut()?;
+                if !press.dragging && (x - press.x).abs() + (y - press.y).abs() < DRAG_THRESHOLD {
+                    return None;
+                }
+                press.dragging = true;
+                // mouse positions are in window coordinates, which differ
+                // from output pixels on high DPI displays
+                let (window_width, _) = self.canvas.window().size();
+                let (output_width, _) = self.canvas.output_size().ok()?;
+                let scale = output_width as f64 / window_width.max(1) as f64;
+                let view = self.view.as_mut()?;
+                view.pan = dragged(view, (xrel as f64 * scale, yrel as f64 * scale));
+                Some(WindowCommand::Pan(Pan::To(view.pan.0, view.pan.1)))
+            }
+            // a click on the left half goes back, on the right half forward
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                x,
+                ..
+            } => match self.press.take() {
+                Some(press) if !press.dragging => {
+                    let (width, _) = self.canvas.window().size();
+                    if (x as u32) < width / 2 {
+                        Some(WindowCommand::Prev)
+                    } else {
+                        Some(WindowCommand::Next)
+                    }
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Command bound to a key of the display, if any
+fn key(keycode: Keycode) -> Option<WindowCommand> {
+    let command = match keycode {
+        Keycode::Right | Keycode::Space | Keycode::PageDown => WindowCommand::Next,
+        Keycode::Left | Keycode::Backspace | Keycode::PageUp => WindowCommand::Prev,
+        Keycode::F => WindowCommand::Fullscreen,
+        Keycode::R => WindowCommand::Rotate,
+        Keycode::P => WindowCommand::Pageant(vec![PageantControl::Toggle]),
+        Keycode::A => WindowCommand::Animation(AnimationControl::Toggle),
+        Keycode::Period => WindowCommand::Animation(AnimationControl::Next),
+        Keycode::Comma => WindowCommand::Animation(AnimationControl::Prev),
+        Keycode::Plus | Keycode::Equals | Keycode::KpPlus => WindowCommand::Zoom(Zoom::In),
+        Keycode::Minus | Keycode::KpMinus => WindowCommand::Zoom(Zoom::Out),
+        Keycode::Num0 | Keycode::Kp0 => WindowCommand::Zoom(Zoom::Reset),
+        // as in the tui
+        Keycode::H => WindowCommand::Pan(Pan::Left),
+        Keycode::J => WindowCommand::Pan(Pan::Down),
+        Keycode::K => WindowCommand::Pan(Pan::Up),
+        Keycode::L => WindowCommand::Pan(Pan::Right),
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// Pan which keeps the point of the image under the mouse there when the
+/// mouse moves by `delta` output pixels
+fn dragged(view: &View, delta: (f64, f64)) -> (f64, f64) {
+    // size of the image as shown, after rotating it
+    let (width, height) = if view.rotation.rem_euclid(2) == 1 {
+        (view.rect.height(), view.rect.width())
+    } else {
+        (view.rect.width(), view.rect.height())
+    };
+    let x = view.pan.0 - delta.0 / width.max(1) as f64;
+    let y = view.pan.1 - delta.1 / height.max(1) as f64;
+    (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0))
+}
+
+impl Display for SdlWindow {
+    fn show(
+        &mut self,
+        path: &Path,
+        image: &Arc<Decoded>,
+        frame: usize,
+        state: &WindowState,
+    ) -> Result<()> {
+        self.canvas.clear();
+        let uploaded = match self.uploaded.take() {
+            Some(uploaded) if uploaded.holds(path, image, frame) => uploaded,
+            previous => {
+                // frames of an animation share the size of the texture
+                let mut texture = match previous {
+                    Some(Uploaded { texture, .. })
+                        if (texture.query().width, texture.query().height)
+                            == (image.width, image.height) =>
+                    {
+                        texture
+                    }
+                    _ => self
+                        .texture_creator
+                        .create_texture_static(FORMAT, image.width, image.height)
+                        .map_err(|e| anyhow!("Update Canvas Error: {}", e))?,
+                };
+                texture
+                    .update(None, &image.frames[frame], image.pitch)
+                    .map_err(|e| anyhow!("Update Canvas Error: {}", e))?;
+                Uploaded {
+                    path: path.to_path_buf(),
+                    image: image.clone(),
+                    frame,
+                    texture,
+                }
+            }
+        };
+        let output = self
+            .canvas
+            .output_size()
+            .map_err(|e| anyhow!("Update Canvas Error: {}", e))?;
+        let dst = layout(
+            (image.width, image.height),
+            output,
+            state.rotation() as i64,
+            state.scale(),
+            state.zoom(),
+            state.pan(),
+        );
+        self.canvas
+            .copy_ex(
+                &uploaded.texture,
+                None,
+                Some(dst),
+                state.rotation() * -90_f64,
+                None,
+                false,
+                false,
+            )
+            .map_err(|e| anyhow!("Update Canvas Error: {}", e))?;
+        self.canvas.present();
+        self.view = Some(View {
+            rect: dst,
+            rotation: state.rotation() as i64,
+            pan: state.pan(),
+        });
+        self.uploaded = Some(uploaded);
+        Ok(())
+    }
+    fn clear(&mut self) -> Result<()> {
+        self.uploaded = None;
+        self.canvas.clear();
+        self.canvas.present();
+        self.view = None;
+        Ok(())
+    }
+    fn apply(&mut self, state: &WindowState) -> Result<()> {
+        let window = self.canvas.window_mut();
+        let title = state.title()?;
+        window
+            .set_fullscreen(state.fullscreen())
+            .map_err(|e| anyhow!("Update Window Error: {}", e))?;
+        window
+            .set_title(title)
+            .map_err(|e| anyhow!("Update Window Error: {}", e))?;
+        Ok(())
+    }
+    fn wait_events(&mut self, timeout: Option<Duration>) -> Vec<LocalEvent> {
+        let first = match timeout {
+            Some(timeout) => {
+                let ms = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+                self.event_pump.wait_event_timeout(ms.max(1))
+            }
+            None => Some(self.event_pump.wait_event()),
+        };
+        let events: Vec<Event> = first
+            .into_iter()
+            .chain(self.event_pump.poll_iter())
+            .collect();
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape) | Some(Keycode::Q),
+                    ..
+                } => Some(LocalEvent::Quit),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => key(*keycode).map(LocalEvent::Command),
+                // layout depends on the window size
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => Some(LocalEvent::Resized),
+                // `Wake` only ends the wait
+                event => self.mouse(event).map(LocalEvent::Command),
+            })
+            .collect()
+    }
+    fn waker(&self) -> Waker {
+        let sender = self.events.event_sender();
+        Arc::new(move || {
+            let _ = sender.push_custom_event(Wake);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        assert!(matches!(key(Keycode::Space), Some(WindowCommand::Next)));
+        assert!(matches!(key(Keycode::Left), Some(WindowCommand::Prev)));
+        assert!(matches!(key(Keycode::F), Some(WindowCommand::Fullscreen)));
+        assert!(matches!(
+            key(Keycode::KpPlus),
+            Some(WindowCommand::Zoom(Zoom::In))
+        ));
+        assert!(matches!(
+            key(Keycode::P),
+            Some(WindowCommand::Pageant(ref controls)) if controls == &[PageantControl::Toggle]
+        ));
+        assert!(matches!(
+            key(Keycode::Period),
+            Some(WindowCommand::Animation(AnimationControl::Next))
+        ));
+        assert!(key(Keycode::X).is_none());
+    }
+
+    #[test]
+    fn test_dragged() {
+        let view = View {
+            rect: Rect::new(0, 0, 1600, 400),
+            rotation: 0,
+            pan: (0.5, 0.5),
+        };
+        // dragging right by a tenth of the image shows more of its left
+        assert_eq!(dragged(&view, (160.0, -40.0)), (0.4, 0.6));
+        assert_eq!(dragged(&view, (-16000.0, 0.0)), (1.0, 0.5));
+        // a quarter turn swaps the dimensions as shown
+        let view = View {
+            rotation: 1,
+            ..view
+        };
+        assert_eq!(dragged(&view, (40.0, 0.0)), (0.4, 0.5));
+    }
+}
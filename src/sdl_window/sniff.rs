@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Image formats SDL_image can decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Avif,
+    Bmp,
+    Cur,
+    Gif,
+    Ico,
+    Jpeg,
+    Jxl,
+    Lbm,
+    Pcx,
+    Png,
+    Pnm,
+    Qoi,
+    Svg,
+    Tga,
+    Tiff,
+    Webp,
+    Xcf,
+    Xpm,
+}
+
+/// Bytes read from the start of a file to detect its format
+const HEADER_LEN: usize = 256;
+
+/// Detect the format of the file at `path` from its first bytes, without
+/// decoding it. Returns `None` for unreadable files and unsupported
+/// formats.
+pub fn sniff(path: &Path) -> Option<Format> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)
+        .ok()?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+    detect(&header).or_else(|| {
+        // TGA has no signature, SDL_image relies on the extension too
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        (extension == "tga").then_some(Format::Tga)
+    })
+}
+
+/// Detect the format from the first bytes of a file
+fn detect(header: &[u8]) -> Option<Format> {
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+    let format = match header {
+        [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, ..] => Format::Png,
+        [0xff, 0xd8, 0xff, ..] => Format::Jpeg,
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Format::Gif,
+        [b'B', b'M', ..] => Format::Bmp,
+        [b'I', b'I', 0x2a, 0x00, ..] | [b'M', b'M', 0x00, 0x2a, ..] => Format::Tiff,
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Format::Webp,
+        [0x00, 0x00, 0x01, 0x00, ..] => Format::Ico,
+        [0x00, 0x00, 0x02, 0x00, ..] => Format::Cur,
+        [b'q', b'o', b'i', b'f', ..] => Format::Qoi,
+        [0xff, 0x0a, ..] => Format::Jxl,
+        [b'P', b'1'..=b'6', ..] => Format::Pnm,
+        [0x0a, 0 | 2..=5, 1, ..] => Format::Pcx,
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'I', b'L', b'B', b'M', ..]
+        | [b'F', b'O', b'R', b'M', _, _, _, _, b'P', b'B', b'M', b' ', ..] => Format::Lbm,
+        _ if at(4, b"ftypavif") || at(4, b"ftypavis") => Format::Avif,
+        _ if at(4, b"JXL \r\n\x87\n") => Format::Jxl,
+        _ if at(0, b"gimp xcf") => Format::Xcf,
+        _ if at(0, b"/* XPM */") => Format::Xpm,
+        _ if header.windows(4).any(|w| w == b"<svg") => Format::Svg,
+        _ => return None,
+    };
+    Some(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some(Format::Png));
+        assert_eq!(detect(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some(Format::Jpeg));
+        assert_eq!(detect(b"GIF89a"), Some(Format::Gif));
+        assert_eq!(detect(b"RIFF\x10\0\0\0WEBPVP8 "), Some(Format::Webp));
+        assert_eq!(detect(b"RIFF\x10\0\0\0AVI LIST"), None);
+        assert_eq!(detect(b"\0\0\0\x1cftypavif"), Some(Format::Avif));
+        assert_eq!(detect(b"\0\0\0\x18ftypmp42"), None);
+        assert_eq!(
+            detect(b"<?xml version=\"1.0\"?>\n<svg xmlns="),
+            Some(Format::Svg)
+        );
+        assert_eq!(detect(b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">"), None);
+        assert_eq!(detect(b""), None);
+    }
+}
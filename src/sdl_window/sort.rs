@@ -0,0 +1,142 @@
+use rayon::prelude::*;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::shuffle::Rng;
+use crate::window::SortOrder;
+
+/// Part of a file name compared in natural order
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    /// Run of digits without leading zeros, compared by length first so
+    /// that it is compared by value
+    Number(usize, String),
+    Text(String),
+}
+
+/// What images are sorted by. Ties are broken by path.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    None,
+    Name(Option<OsString>),
+    Natural(Vec<Chunk>),
+    Time(SystemTime),
+    Size(u64),
+    /// Missing capture dates sort last, by modification time
+    Taken(Option<String>, SystemTime),
+}
+
+/// Sort `paths` in `order`.
+pub fn sort(paths: &mut Vec<PathBuf>, order: SortOrder) {
+    let mut keyed: Vec<(Key, PathBuf)> = paths
+        .par_drain(..)
+        .map(|path| (key(&path, order), path))
+        .collect();
+    keyed.par_sort_unstable();
+    paths.extend(keyed.into_iter().map(|(_, path)| path));
+    if let SortOrder::Shuffle(seed) = order {
+        Rng::new(seed).shuffle(paths);
+    }
+}
+
+/// Compare two paths in `order`. Shuffled paths compare by path.
+pub fn compare(a: &Path, b: &Path, order: SortOrder) -> std::cmp::Ordering {
+    (key(a, order), a).cmp(&(key(b, order), b))
+}
+
+fn key(path: &Path, order: SortOrder) -> Key {
+    let modified = || {
+        path.metadata()
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    };
+    match order {
+        SortOrder::Path | SortOrder::Shuffle(_) => Key::None,
+        SortOrder::Name => Key::Name(path.file_name().map(|n| n.to_os_string())),
+        SortOrder::Natural => Key::Natural(chunks(
+            &path.file_name().unwrap_or_default().to_string_lossy(),
+        )),
+        SortOrder::Modified => Key::Time(modified()),
+        SortOrder::Size => Key::Size(path.metadata().map_or(0, |m| m.len())),
+        SortOrder::Taken => match taken(path) {
+            Some(date) => Key::Taken(Some(date), SystemTime::UNIX_EPOCH),
+            None => Key::Taken(None, modified()),
+        },
+    }
+}
+
+/// Split a file name into runs of digits and of other characters
+fn chunks(name: &str) -> Vec<Chunk> {
+    let mut chunks = vec![];
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        let digit = c.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digit)
+            .unwrap_or(rest.len());
+        let (run, tail) = rest.split_at(end);
+        chunks.push(if digit {
+            let digits = run.trim_start_matches('0');
+            Chunk::Number(digits.len(), digits.to_string())
+        } else {
+            Chunk::Text(run.to_lowercase())
+        });
+        rest = tail;
+    }
+    chunks
+}
+
+/// EXIF capture date as `YYYY-MM-DD HH:MM:SS`
+fn taken(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .or_else(|| exif.get_field(exif::Tag::DateTime, exif::In::PRIMARY))?;
+    Some(field.display_value().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(paths: &[&str], order: SortOrder) -> Vec<PathBuf> {
+        let mut paths = paths.iter().map(PathBuf::from).collect();
+        sort(&mut paths, order);
+        paths
+    }
+
+    #[test]
+    fn test_sort_name_breaks_ties_by_path() {
+        let paths = ["b/x.jpg", "a/y.jpg", "a/x.jpg"];
+        let expected: Vec<PathBuf> = ["a/x.jpg", "b/x.jpg", "a/y.jpg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(sorted(&paths, SortOrder::Name), expected);
+    }
+
+    #[test]
+    fn test_sort_natural() {
+        let paths = ["img10.jpg", "IMG2.jpg", "img1.jpg", "img002b.jpg"];
+        let expected: Vec<PathBuf> = ["img1.jpg", "IMG2.jpg", "img002b.jpg", "img10.jpg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(sorted(&paths, SortOrder::Natural), expected);
+    }
+
+    #[test]
+    fn test_sort_shuffle_is_seeded() {
+        let paths: Vec<String> = (0..20).map(|i| format!("{}.jpg", i)).collect();
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let a = sorted(&paths, SortOrder::Shuffle(1));
+        assert_eq!(a, sorted(&paths, SortOrder::Shuffle(1)));
+        assert_ne!(a, sorted(&paths, SortOrder::Shuffle(2)));
+    }
+}
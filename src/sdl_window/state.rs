@@ -2,6 +2,15 @@ use anyhow::{anyhow, Result};
 use sdl2::video::FullscreenType;
 use std::ffi::{OsStr, OsString};
 
+use crate::window::{Pan, ScaleMode, Zoom};
+
+/// Factor applied by each zoom step
+const ZOOM_STEP: f64 = 1.25;
+/// Bounds of the zoom factor
+const ZOOM_RANGE: (f64, f64) = (0.1, 32.0);
+/// Fraction of the window each pan step moves the view
+const PAN_STEP: f64 = 0.1;
+
 /// Struct to hold Window State
 pub struct WindowState {
     /// Degrees to rotate the image
@@ -10,6 +19,13 @@ pub struct WindowState {
     fullscreen: FullscreenType,
     /// Title shown at the top of the window
     title: OsString,
+    /// How the image is scaled to the window
+    scale: ScaleMode,
+    /// Magnification on top of the scaling mode
+    zoom: f64,
+    /// Point of the image shown at the center of the window, as
+    /// fractions of its width and height
+    pan: (f64, f64),
 }
 
 impl WindowState {
@@ -21,6 +37,9 @@ impl WindowState {
             title,
             fullscreen,
             rotation,
+            scale: ScaleMode::default(),
+            zoom: 1.0,
+            pan: (0.5, 0.5),
         }
     }
     pub fn fullscreen(&self) -> FullscreenType {
@@ -29,6 +48,15 @@ impl WindowState {
     pub fn rotation(&self) -> f64 {
         self.rotation
     }
+    pub fn scale(&self) -> ScaleMode {
+        self.scale
+    }
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+    pub fn pan(&self) -> (f64, f64) {
+        self.pan
+    }
     pub fn title(&self) -> Result<&str> {
         let title = self
             .title
@@ -48,6 +76,38 @@ impl WindowState {
         self.rotation += f;
         self.rotation
     }
+    pub fn set_scale(&mut self, scale: ScaleMode) {
+        self.scale = scale;
+    }
+    pub fn apply_zoom(&mut self, zoom: Zoom) -> f64 {
+        let (min, max) = ZOOM_RANGE;
+        match zoom {
+            Zoom::In => self.zoom = (self.zoom * ZOOM_STEP).min(max),
+            Zoom::Out => self.zoom = (self.zoom / ZOOM_STEP).max(min),
+            Zoom::Reset => self.reset_view(),
+        };
+        self.zoom
+    }
+    /// Pan steps move the view by the same distance on screen no
+    /// matter the zoom factor.
+    pub fn apply_pan(&mut self, pan: Pan) -> (f64, f64) {
+        let step = PAN_STEP / self.zoom;
+        let (x, y) = self.pan;
+        let (x, y) = match pan {
+            Pan::Up => (x, y - step),
+            Pan::Down => (x, y + step),
+            Pan::Left => (x - step, y),
+            Pan::Right => (x + step, y),
+            Pan::To(x, y) => (x, y),
+        };
+        self.pan = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+        self.pan
+    }
+    /// Show the whole image again
+    pub fn reset_view(&mut self) {
+        self.zoom = 1.0;
+        self.pan = (0.5, 0.5);
+    }
     pub fn set_title(&mut self, s: &OsStr) {
         self.title = s.into();
     }
@@ -1,30 +1,31 @@
 use anyhow::{anyhow, Result};
-use sdl2::EventPump;
-
-use sdl2::image::LoadTexture;
-use sdl2::render::WindowCanvas;
+use sdl2::video::FullscreenType;
 use std::path::Path;
 use tracing::debug;
 
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Receiver;
 
 use crate::db::Db;
 use crate::serve::Config;
-use crate::window::WindowCommand;
+use crate::window::{
+    FileEvent, PageantControl, Position, SortOrder, Status, Target, WindowCommand, DISPLAY_PATH,
+    FILTER, FULLSCREEN, PAGEANT, ROTATION, SCALE, SORT, ZOOM,
+};
 
-use super::navigator::Navigator;
-use super::pageant::PageantMode;
+use super::animation::Playback;
+use super::display::{Display, LocalEvent};
+use super::navigator::{Navigator, Outcome};
+use super::pageant::{PageantMode, Step};
+use super::prefetch::Prefetcher;
+use super::sniff::sniff;
 use super::WindowState;
 
-/// Wrapper for all Window related logic.
-pub struct SdlWindow {
-    /// Sdl Window Canvas to draw images on
-    canvas: WindowCanvas,
-    /// Listener to receive commands from the Window
-    event_pump: EventPump,
+/// Wrapper for all Window related logic, showing the images on `D`.
+pub struct Window<D: Display> {
+    /// Where the images are shown
+    display: D,
     /// Cursor of file paths to display in the Window
     cursor: Navigator,
     /// Receiver for Commands received over TCP
@@ -38,65 +39,76 @@ pub struct SdlWindow {
     db: Db,
     /// Logic to automatically advance cursor
     pageant: PageantMode,
+    /// Decodes and caches images around the current one
+    prefetch: Prefetcher,
+    /// Number of images to decode ahead on each side of the current one
+    prefetch_count: usize,
+    /// Frames of the current image
+    playback: Playback,
+    /// Width and height of the image shown, zero while none is
+    dimensions: (u32, u32),
+    /// Settings the server was started or last reloaded with
+    config: Arc<Config>,
 }
 
-impl SdlWindow {
+impl<D: Display> Window<D> {
     pub(crate) fn new(
-        title: &str,
+        display: D,
         path: &Path,
         rx: Receiver<WindowCommand>,
         db: Db,
         config: Arc<Config>,
     ) -> Result<Self> {
-        let state = WindowState::new(title);
-        let cursor = Navigator::new(path)?;
+        let state = WindowState::new("viewd");
+        let order = config.sort.parse().map_err(|e| anyhow!("{}", e))?;
+        let cursor = Navigator::new(path, order, config.filter.clone())?;
         let pageant = PageantMode::new(config.pageant_wait);
-        let sdl_context = sdl2::init().map_err(|e| anyhow!("Navigator init Error: {}", e))?;
-        let event_pump = sdl_context
-            .event_pump()
-            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
-        let video_subsystem = sdl_context
-            .video()
-            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
-        let window = video_subsystem
-            .window("viewd", 800, 600)
-            .position_centered()
-            .resizable()
-            .build()
-            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
-
-        let canvas = window
-            .into_canvas()
-            .present_vsync()
-            .software()
-            .target_texture()
-            .build()
-            .map_err(|e| anyhow!("Navigator init Error: {}", e))?;
-
+        let prefetch = Prefetcher::new(config.decode_threads, config.cache_size * 1024 * 1024)?;
         let shutdown = Arc::new(Mutex::new(false));
         let s = Self {
-            canvas,
-            event_pump,
+            display,
             cursor,
             rx,
             shutdown,
             state,
             db,
             pageant,
+            prefetch,
+            prefetch_count: config.prefetch,
+            playback: Playback::new(Path::new(""), vec![], Instant::now()),
+            dimensions: (0, 0),
+            config,
         };
 
         Ok(s)
     }
     fn next(&mut self) -> Result<()> {
-        self.cursor.next().ok_or(anyhow!("no image found"))?;
-        self.update_title();
-        self.update_window()?;
-        Ok(())
+        let outcome = self.cursor.next(supported);
+        self.moved(outcome)
     }
     fn prev(&mut self) -> Result<()> {
-        self.cursor.prev();
-        self.update_title();
-        self.update_window()?;
+        let outcome = self.cursor.prev(supported);
+        self.moved(outcome)
+    }
+    /// Update the window after moving to another image
+    fn moved(&mut self, outcome: Outcome) -> Result<()> {
+        match outcome {
+            Outcome::Found(_) => {
+                self.state.reset_view();
+                self.update_title();
+                self.update_window()?;
+            }
+            // nothing else to show, stay on the current image
+            Outcome::OnlyOne => {}
+            // keep running with an empty display, images may be added
+            // to the directory again
+            Outcome::Exhausted => {
+                debug!("no image left to show");
+                self.state.reset_view();
+                self.update_title();
+                self.update_window()?;
+            }
+        }
         Ok(())
     }
     /// wraps update methods
@@ -107,108 +119,373 @@ impl SdlWindow {
         Ok(())
     }
     pub(crate) fn init(&mut self) -> Result<()> {
-        self.cursor.next().ok_or(anyhow!("no image found"))?;
+        if self.cursor.next(supported) == Outcome::Exhausted {
+            return Err(anyhow!("no image found"));
+        }
         self.update()?;
+        self.publish_state();
         Ok(())
     }
     /// Update window_title on Self and `display_path` in db.
     fn update_title(&mut self) {
         if let Some(name) = self.cursor.image.file_name() {
-            self.db.set("display_path".into(), name.into());
+            self.db.set(DISPLAY_PATH.into(), name.into());
             self.state.set_title(name);
+        } else if !self.cursor.has_image() {
+            self.db.set(DISPLAY_PATH.into(), "".into());
+            self.state.set_title("viewd".as_ref());
         }
     }
-    fn update_canvas(&mut self) -> Result<()> {
-        self.canvas.clear();
-        let texture_creator = self.canvas.texture_creator();
-        let current = self.cursor.image.clone();
-        if let Ok(texture) = texture_creator.load_texture(self.cursor.image.clone()) {
-            self.canvas
-                .copy_ex(
-                    &texture,
-                    None,
-                    None,
-                    self.state.rotation() * -90_f64,
-                    None,
-                    false,
-                    false,
-                )
-                .map_err(|e| anyhow!("Update Canvas Error: {}", e))?;
-            self.canvas.present();
-        } else {
-            // log image name for debugging purposes and silently fail
-            // user will have to call next() again
-            debug!("Could not load path: {}", current.display());
+    /// Store fullscreen, rotation, pageant, scale and zoom state in db so clients
+    /// can read it and subscribers are notified of changes.
+    fn publish_state(&self) {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let fullscreen = self.state.fullscreen() != FullscreenType::Off;
+        let degrees = (self.state.rotation() * 90.0).rem_euclid(360.0);
+        self.db.set(FULLSCREEN.into(), on_off(fullscreen).into());
+        self.db.set(ROTATION.into(), degrees.to_string().into());
+        self.db.set(PAGEANT.into(), self.pageant.to_string().into());
+        self.db
+            .set(SCALE.into(), self.state.scale().to_string().into());
+        self.db
+            .set(ZOOM.into(), self.state.zoom().to_string().into());
+        self.db
+            .set(SORT.into(), self.cursor.order().to_string().into());
+        self.db
+            .set(FILTER.into(), self.cursor.filter().to_string().into());
+    }
+    /// Describe the window and the current image for `INFO`
+    fn info(&self) -> Status {
+        let position = self.cursor.position();
+        // as shown, the image is not decoded again just for this
+        let (width, height) = self.dimensions;
+        let file_size = std::fs::metadata(&self.cursor.image).map_or(0, |m| m.len());
+        Status {
+            index: position.index,
+            total: position.total,
+            path: position.path,
+            width,
+            height,
+            file_size,
+            rotation: (self.state.rotation() * 90.0).rem_euclid(360.0),
+            fullscreen: self.state.fullscreen() != FullscreenType::Off,
+            scale: self.state.scale(),
+            zoom: self.state.zoom(),
+            sort: self.cursor.order(),
+            pageant: self.pageant.is_active(),
+            pageant_wait: self.pageant.timeout(),
+            pageant_direction: self.pageant.direction(),
+            pageant_order: self.pageant.order(),
+            unsupported: self.cursor.unsupported(),
+            frame: self.playback.frame(),
+            frames: self.playback.frames(),
+            paused: self.playback.is_paused(),
+            ..Status::default()
+        }
+    }
+    /// Show the current frame of the current image. Images which can not
+    /// be decoded, e.g. truncated files with a valid header, are dropped
+    /// from the collection and the next one is shown instead.
+    fn draw(&mut self) -> Result<()> {
+        loop {
+            if !self.cursor.has_image() {
+                self.playback = Playback::new(Path::new(""), vec![], Instant::now());
+                self.dimensions = (0, 0);
+                return self.display.clear();
+            }
+            match self.prefetch.load(&self.cursor.image) {
+                Ok(image) => {
+                    // another image, or the same one changed on disk, plays
+                    // from the start
+                    if self.playback.path() != self.cursor.image
+                        || self.playback.frames() != image.frames.len()
+                    {
+                        self.playback =
+                            Playback::new(&self.cursor.image, image.delays.clone(), Instant::now());
+                    }
+                    let frame = self.playback.frame();
+                    self.dimensions = (image.width, image.height);
+                    return self
+                        .display
+                        .show(&self.cursor.image, &image, frame, &self.state);
+                }
+                Err(e) => {
+                    debug!("Could not load path {}: {}", self.cursor.image.display(), e);
+                    // every round removes an image, so this ends at the
+                    // latest once the collection is empty
+                    self.cursor.discard();
+                    self.next()?;
+                }
+            }
         }
+    }
+    fn update_canvas(&mut self) -> Result<()> {
+        self.draw()?;
+        // decode the images the user is likely to move to next
+        let neighbours = self.cursor.neighbours(self.prefetch_count);
+        self.prefetch.request(&neighbours);
         Ok(())
     }
     fn update_window(&mut self) -> Result<()> {
-        let window = self.canvas.window_mut();
-        let title = self.state.title()?;
-        window
-            .set_fullscreen(self.state.fullscreen())
-            .map_err(|e| anyhow!("Update Window Error: {}", e))?;
-        window
-            .set_title(title)
-            .map_err(|e| anyhow!("Update Window Error: {}", e))?;
-        Ok(())
+        self.display.apply(&self.state)
     }
     fn _try_load(&mut self, image: &Path) -> Option<()> {
-        let texture_creator = self.canvas.texture_creator();
-        texture_creator.load_texture(image).ok().map(|_| ())
+        self.prefetch.load(image).ok().map(|_| ())
     }
-    /// Update image if we in pageant mode and timeout has elapsed
+    /// Update image if we in pageant mode and timeout has elapsed, once an
+    /// animated image has been played through
     pub fn pageant(&mut self) -> Result<()> {
-        if self.pageant.should_update() {
+        // nothing to step through until images are added again
+        if self.cursor.is_empty() {
+            return Ok(());
+        }
+        if self.pageant.should_update() && self.playback.looped() {
             self.pageant.set_instant();
-            self.next()?;
+            let position = self.cursor.position();
+            match self.pageant.step(position.index, position.total) {
+                Step::Next => self.next()?,
+                Step::Prev => self.prev()?,
+                Step::Goto(index) => {
+                    // skip images which can not be shown
+                    if self.goto(&Target::Index(index))?.is_err() {
+                        self.next()?;
+                    }
+                }
+            }
             self.update_canvas()?;
         };
         Ok(())
     }
+    /// Show another image if the current one is gone after a rescan and
+    /// reply with the position, or why the rescan failed.
+    fn rescanned(&mut self, gone: Result<bool>) -> Result<Result<Position, String>> {
+        match gone {
+            Ok(gone) => {
+                if gone {
+                    self.next()?;
+                }
+                self.update_title();
+                Ok(Ok(self.cursor.position()))
+            }
+            Err(e) => Ok(Err(e.to_string())),
+        }
+    }
+    /// Apply a reloaded config. Only settings which changed in the config
+    /// file are applied, so changes made at runtime to the others are kept.
+    /// The inner result fails if the config can not be applied, in which
+    /// case nothing is changed.
+    fn configure(&mut self, config: Arc<Config>) -> Result<Result<(), String>> {
+        // check every setting before applying any
+        let order: SortOrder = match config.sort.parse() {
+            Ok(order) => order,
+            Err(e) => return Ok(Err(format!("invalid sort: {}", e))),
+        };
+        let scan = if config.filter != self.config.filter {
+            match self.cursor.scan(config.filter.clone()) {
+                Ok(scan) => Some(scan),
+                Err(e) => return Ok(Err(format!("invalid filter: {}", e))),
+            }
+        } else {
+            None
+        };
+        let prefetch = if config.decode_threads != self.config.decode_threads
+            || config.cache_size != self.config.cache_size
+        {
+            match Prefetcher::new(config.decode_threads, config.cache_size * 1024 * 1024) {
+                Ok(prefetch) => Some(prefetch),
+                Err(e) => return Ok(Err(e.to_string())),
+            }
+        } else {
+            None
+        };
 
-    /// Handle Commands received from mpsc channel as well as minimal
-    /// SDL Window Events.
-    pub(crate) fn handle_event(&mut self) -> Result<()> {
-        loop {
-            if *self.shutdown.lock().unwrap() {
-                break Ok(());
+        if let Some(prefetch) = prefetch {
+            self.prefetch = prefetch;
+        }
+        if let Some(scan) = scan {
+            if self.cursor.apply_scan(scan) {
+                self.next()?;
             }
-            while let Ok(command) = self.rx.try_recv() {
-                debug!(?command);
+            self.update_title();
+        }
+        if config.sort != self.config.sort {
+            self.cursor.set_order(order);
+        }
+        if config.pageant_wait != self.config.pageant_wait {
+            self.pageant
+                .control(PageantControl::Interval(config.pageant_wait));
+        }
+        self.prefetch_count = config.prefetch;
+        self.config = config;
+        Ok(Ok(()))
+    }
+    /// Move to `target`. The outer result fails if the window could not be
+    /// updated, the inner one if there is no such image.
+    fn goto(&mut self, target: &Target) -> Result<Result<Position>> {
+        let position = self.cursor.goto(target, supported);
+        if position.is_ok() {
+            self.state.reset_view();
+            self.update_title();
+            self.update_window()?;
+        }
+        Ok(position)
+    }
+
+    /// Carry out `command`, whether it was sent by a client or comes from
+    /// the keyboard or mouse of the display, and redraw
+    fn command(&mut self, command: WindowCommand) -> Result<()> {
+        debug!(?command);
 
-                match command {
-                    WindowCommand::Quit => *self.shutdown.lock().unwrap() = true,
-                    WindowCommand::Next => self.next()?,
-                    WindowCommand::Prev => self.prev()?,
-                    WindowCommand::Fullscreen => {
-                        self.state.toggle_fullscreen();
-                        self.update_window()?;
+        match command {
+            WindowCommand::Quit => *self.shutdown.lock().unwrap() = true,
+            WindowCommand::Next => self.next()?,
+            WindowCommand::Prev => self.prev()?,
+            WindowCommand::Fullscreen => {
+                self.state.toggle_fullscreen();
+                self.update_window()?;
+            }
+            WindowCommand::Rotate => {
+                self.state.rotate(1.0);
+            }
+            WindowCommand::Pageant(controls) => {
+                for control in controls {
+                    self.pageant.control(control);
+                }
+            }
+            WindowCommand::Scale(mode) => {
+                self.state.set_scale(mode);
+            }
+            WindowCommand::Zoom(zoom) => {
+                self.state.apply_zoom(zoom);
+            }
+            WindowCommand::Pan(pan) => {
+                self.state.apply_pan(pan);
+            }
+            WindowCommand::Animation(control) => {
+                self.playback.control(control, Instant::now());
+            }
+            WindowCommand::Goto(target, reply) => {
+                let position = self.goto(&target)?;
+                let _ = reply.send(position.map_err(|e| e.to_string()));
+            }
+            WindowCommand::Info(reply) => {
+                let _ = reply.send(self.info());
+                // nothing changed, skip redrawing
+                return Ok(());
+            }
+            WindowCommand::List(query, reply) => {
+                let _ = reply.send(self.cursor.list(&query));
+                // nothing changed, skip redrawing
+                return Ok(());
+            }
+            WindowCommand::File(event) => {
+                match &event {
+                    FileEvent::Created(path) | FileEvent::Removed(path) => {
+                        self.prefetch.invalidate(path)
                     }
-                    WindowCommand::Rotate => {
-                        self.state.rotate(1.0);
+                    FileEvent::Renamed(from, to) => {
+                        self.prefetch.invalidate(from);
+                        self.prefetch.invalidate(to);
                     }
-                    WindowCommand::Pageant => {
-                        self.pageant.toggle();
+                }
+                let gone = self.cursor.apply(&event);
+                // move on from a removed image, or onto the first image
+                // added to an empty collection
+                if gone || !self.cursor.has_image() {
+                    self.next()?;
+                }
+                self.update_title();
+            }
+            WindowCommand::Sort(order, reply) => {
+                let _ = reply.send(self.cursor.set_order(order));
+                // the image stays the same, only its index changes
+                self.publish_state();
+                return Ok(());
+            }
+            WindowCommand::Reload(reply) => {
+                let gone = self.cursor.reload();
+                let _ = reply.send(self.rescanned(gone)?);
+            }
+            WindowCommand::Filter(settings, reply) => {
+                let mut filter = self.cursor.filter().clone();
+                let mut changed = Ok(());
+                for setting in &settings {
+                    if setting == "reset" {
+                        filter = self.config.filter.clone();
+                    } else if let Err(e) = filter.set(setting) {
+                        changed = Err(anyhow!("{}", e));
+                        break;
                     }
                 }
-                // This is called for no reason in the case of Quit and Pageant
-                self.update_canvas()?;
+                let gone = changed.and_then(|_| self.cursor.set_filter(filter));
+                let _ = reply.send(self.rescanned(gone)?);
+            }
+            WindowCommand::Configure(config, reply) => {
+                let applied = self.configure(config)?;
+                let _ = reply.send(applied);
+            }
+            WindowCommand::Open(path) => {
+                self.cursor.open(path);
+                self.state.reset_view();
+                self.update_title();
+                self.update_window()?;
+            }
+        }
+        // This is called for no reason in the case of Quit and Pageant
+        self.update_canvas()?;
+        self.publish_state();
+        Ok(())
+    }
+
+    /// Handle Commands received from mpsc channel as well as the events
+    /// of the display. The display has to be woken up whenever a command
+    /// is sent, as the loop sleeps while nothing happens.
+    pub(crate) fn handle_event(&mut self) -> Result<()> {
+        loop {
+            while let Ok(command) = self.rx.try_recv() {
+                self.command(command)?;
+            }
+            if *self.shutdown.lock().unwrap() {
+                break Ok(());
             }
             // we check if image needs updating on every iteration
             self.pageant()?;
 
-            for event in self.event_pump.poll_iter() {
+            // sleep until a command arrives, something happens on the
+            // display or the next frame or image is due
+            let mut resized = false;
+            for event in self.display.wait_events(self.deadline()) {
                 match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape) | Some(Keycode::Q),
-                        ..
-                    } => *self.shutdown.lock().unwrap() = true,
-                    _ => {}
-                };
-            }
-            std::thread::sleep(std::time::Duration::from_millis(10));
+                    LocalEvent::Quit => *self.shutdown.lock().unwrap() = true,
+                    LocalEvent::Resized => resized = true,
+                    // keys and mouse act like commands sent by a client
+                    LocalEvent::Command(command) => self.command(command)?,
+                }
+            }
+            let advanced = self.playback.advance(Instant::now());
+            if resized || advanced {
+                self.draw()?;
+            }
+        }
+    }
+    /// Time until the next frame of an animated image is due or, once it
+    /// has been played through, the next image in pageant mode
+    fn deadline(&self) -> Option<Duration> {
+        let frame = self.playback.remaining(Instant::now());
+        let image = self
+            .pageant
+            .remaining()
+            .filter(|_| self.playback.looped() && !self.cursor.is_empty());
+        match (frame, image) {
+            (Some(frame), Some(image)) => Some(frame.min(image)),
+            (frame, image) => frame.or(image),
         }
     }
 }
+
+/// Check that `path` is still a supported image without decoding it
+fn supported(path: &Path) -> Result<()> {
+    sniff(path)
+        .map(|_| ())
+        .ok_or(anyhow!("not a supported image format"))
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use tokio_rustls::rustls::Certificate;
+use x509_parser::extensions::GeneralName;
+
+/// What a client is allowed to do. Each role may do everything the
+/// roles before it may do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde_derive::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read the state of the server: `GET`, `INFO`, `SUBSCRIBE`, `LIST`
+    Viewer,
+    /// Move through and arrange the images: `SET`, `GOTO`, `SORT`, `FILTER`
+    Controller,
+    /// Change the image directory: `UPLOAD`, `RELOAD`
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::Viewer => write!(f, "viewer"),
+            Role::Controller => write!(f, "controller"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Roles of clients by the names in their TLS certificate, i.e. the
+/// subject common name and the DNS and e-mail subject alternative names.
+#[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Acl {
+    /// role of clients which are not listed
+    pub default: Role,
+    pub admin: Vec<String>,
+    pub controller: Vec<String>,
+    pub viewer: Vec<String>,
+}
+
+impl Default for Acl {
+    /// Every client is an admin, as before roles existed
+    fn default() -> Self {
+        Self {
+            default: Role::Admin,
+            admin: vec![],
+            controller: vec![],
+            viewer: vec![],
+        }
+    }
+}
+
+impl Acl {
+    /// Role of a client known by `names`. A client listed for several
+    /// roles gets the highest one. Names are compared ignoring case.
+    pub fn role(&self, names: &[String]) -> Role {
+        let listed = |list: &[String]| {
+            list.iter()
+                .any(|entry| names.iter().any(|name| name.eq_ignore_ascii_case(entry)))
+        };
+        if listed(&self.admin) {
+            Role::Admin
+        } else if listed(&self.controller) {
+            Role::Controller
+        } else if listed(&self.viewer) {
+            Role::Viewer
+        } else {
+            self.default
+        }
+    }
+}
+
+/// Names identifying the owner of a client certificate: the subject common
+/// names followed by the DNS and e-mail subject alternative names.
+pub fn peer_names(cert: &Certificate) -> Vec<String> {
+    let cert = match x509_parser::parse_x509_certificate(&cert.0) {
+        Ok((_, cert)) => cert,
+        Err(_) => return vec![],
+    };
+    let mut names: Vec<String> = cert
+        .subject()
+        .iter_common_name()
+        .filter_map(|cn| cn.as_str().ok())
+        .map(String::from)
+        .collect();
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            match name {
+                GeneralName::DNSName(name) | GeneralName::RFC822Name(name) => {
+                    names.push(name.to_string())
+                }
+                _ => {}
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acl_role() {
+        let acl = Acl {
+            default: Role::Viewer,
+            admin: vec!["laptop.home".into()],
+            controller: vec!["tablet.home".into(), "laptop.home".into()],
+            viewer: vec![],
+        };
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(acl.role(&names(&["Laptop.Home"])), Role::Admin);
+        assert_eq!(
+            acl.role(&names(&["tablet", "tablet.home"])),
+            Role::Controller
+        );
+        assert_eq!(acl.role(&names(&["phone"])), Role::Viewer);
+        assert_eq!(acl.role(&[]), Role::Viewer);
+        assert_eq!(Acl::default().role(&[]), Role::Admin);
+        assert!(Role::Viewer < Role::Controller && Role::Controller < Role::Admin);
+    }
+}
@@ -1,16 +1,22 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use config::Config as Configurator;
 use std::{
+    net::{IpAddr, SocketAddr},
     path::{Path, PathBuf},
     sync::Arc,
 };
 
+use super::Acl;
+use crate::window::Filter;
 use crate::DEFAULT_PORT;
 
 #[derive(Clone, Debug, serde_derive::Deserialize, PartialEq, Eq)]
 pub struct Config {
     #[serde(default = "default_port")]
     pub port: u16,
+    /// addresses to listen on, IPv4 or IPv6, optionally with a port
+    #[serde(default = "default_bind")]
+    pub bind: Vec<String>,
     #[serde(rename = "tls-key-file")]
     pub key: PathBuf,
     /// end-entity certificate of server
@@ -18,10 +24,32 @@ pub struct Config {
     pub cert: PathBuf,
     #[serde(rename = "ca-file")]
     pub ca: PathBuf,
+    /// revoked client certificates, reloaded when the file changes
+    #[serde(rename = "crl-file")]
+    pub crl: Option<PathBuf>,
     pub path: PathBuf,
     /// milliseconds to wait between updating image in pageant mode
     #[serde(rename = "pageant-wait", default = "default_pageant_wait")]
     pub pageant_wait: u64,
+    /// number of images decoded ahead on each side of the current one
+    #[serde(default = "default_prefetch")]
+    pub prefetch: usize,
+    /// threads decoding images in the background
+    #[serde(rename = "decode-threads", default = "default_decode_threads")]
+    pub decode_threads: usize,
+    /// order of the images: name, path, natural, modified, size, taken or
+    /// shuffle, optionally seeded as in `shuffle:42`
+    #[serde(default = "default_sort")]
+    pub sort: String,
+    /// files under `path` which are part of the collection
+    #[serde(default)]
+    pub filter: Filter,
+    /// MiB of memory decoded images may take up
+    #[serde(rename = "cache-size", default = "default_cache_size")]
+    pub cache_size: usize,
+    /// roles of clients by the names in their certificates
+    #[serde(default)]
+    pub acl: Acl,
 }
 
 impl Config {
@@ -32,18 +60,61 @@ impl Config {
             .build()?;
 
         let config = settings.try_deserialize::<Config>()?;
+        config.filter.check().map_err(|e| anyhow!("{}", e))?;
         Ok(Arc::new(config))
     }
 }
 
+/// Resolve `bind` entries to socket addresses. Entries without a port
+/// use `port`. IPv6 addresses with a port must be bracketed, as in
+/// `[::1]:6379`.
+pub fn bind_addrs(bind: &[String], port: u16) -> Result<Vec<SocketAddr>> {
+    if bind.is_empty() {
+        return Err(anyhow!("no bind address given"));
+    }
+    bind.iter()
+        .map(|s| {
+            if let Ok(addr) = s.parse::<SocketAddr>() {
+                return Ok(addr);
+            }
+            let ip = s
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<IpAddr>()
+                .map_err(|_| anyhow!("invalid bind address: {}", s))?;
+            Ok(SocketAddr::new(ip, port))
+        })
+        .collect()
+}
+
 fn default_port() -> u16 {
     DEFAULT_PORT
 }
 
+fn default_bind() -> Vec<String> {
+    vec!["127.0.0.1".to_string()]
+}
+
 fn default_pageant_wait() -> u64 {
     1000
 }
 
+fn default_sort() -> String {
+    "name".to_string()
+}
+
+fn default_prefetch() -> usize {
+    2
+}
+
+fn default_decode_threads() -> usize {
+    2
+}
+
+fn default_cache_size() -> usize {
+    512
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,4 +125,27 @@ mod tests {
         assert_eq!(DEFAULT_PORT, c.port);
         Ok(())
     }
+
+    #[test]
+    fn test_bind_addrs() -> Result<()> {
+        let bind = vec![
+            "127.0.0.1".to_string(),
+            "::".to_string(),
+            "[::1]".to_string(),
+            "0.0.0.0:7000".to_string(),
+            "[fe80::1]:7001".to_string(),
+        ];
+        let addrs = bind_addrs(&bind, 6379)?;
+        let expected: Vec<SocketAddr> = vec![
+            "127.0.0.1:6379".parse()?,
+            "[::]:6379".parse()?,
+            "[::1]:6379".parse()?,
+            "0.0.0.0:7000".parse()?,
+            "[fe80::1]:7001".parse()?,
+        ];
+        assert_eq!(expected, addrs);
+        assert!(bind_addrs(&["localhost".to_string()], 6379).is_err());
+        assert!(bind_addrs(&[], 6379).is_err());
+        Ok(())
+    }
 }
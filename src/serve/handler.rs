@@ -1,19 +1,34 @@
+use super::{Reloader, Tls};
 use crate::db::Db;
 use crate::shutdown::Shutdown;
 use crate::window::WindowCommand;
 use crate::{Command, Connection};
 
-use tokio::sync::mpsc;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
+use tokio::sync::{mpsc, watch};
+use tokio_rustls::rustls::Certificate;
 
-use tracing::debug;
+use tracing::{debug, info};
 
 /// Per-connection handler. Reads requests from `connection` and applies the
 /// commands.
 #[derive(Debug)]
 pub(crate) struct Handler {
     pub db: Db,
+    /// Names in the client certificate, which the ACL of the current
+    /// config maps to what the client may do
+    pub names: Vec<String>,
+    /// Certificate the client authenticated with
+    pub peer: Option<Certificate>,
+    pub tls: Arc<Tls>,
+    pub reloader: Arc<Reloader>,
+    /// Notified when the TLS configuration was reloaded
+    pub reloaded: watch::Receiver<()>,
     pub connection: Connection,
+    /// Image directory served by the window
+    pub path: PathBuf,
     pub win_cmd_tx: Sender<WindowCommand>,
 
     pub shutdown: Shutdown,
@@ -38,6 +53,10 @@ impl Handler {
                     // This will result in the task terminating.
                     return Ok(());
                 }
+                _ = revoked(&mut self.reloaded, &self.tls, self.peer.as_ref()) => {
+                    info!("client certificate revoked, closing connection");
+                    return Ok(());
+                }
             };
 
             // If `None` is returned from `read_frame()` then the peer closed
@@ -55,16 +74,42 @@ impl Handler {
             // Commands are passed and transmitted to SDL Window over
             // Mpsc channel. Currently only Window mutates the database
             // but it might make sense in the future to add commands
-            // that mutate the db directly.
-            cmd.apply(
-                &self.db,
-                self.win_cmd_tx.clone(),
-                &mut self.connection,
-                &mut self.shutdown,
-            )
-            .await?;
+            // that mutate the db directly. Commands such as SUBSCRIBE
+            // and UPLOAD run for a long time, so revocation is checked
+            // while they do, too.
+            // The role is looked up for every command so that a reloaded
+            // config applies to open connections, too.
+            let role = self.reloader.config().acl.role(&self.names);
+            tokio::select! {
+                res = cmd.apply(
+                    role,
+                    &self.reloader,
+                    &self.db,
+                    &self.path,
+                    self.win_cmd_tx.clone(),
+                    &mut self.connection,
+                    &mut self.shutdown,
+                ) => res?,
+                _ = revoked(&mut self.reloaded, &self.tls, self.peer.as_ref()) => {
+                    info!("client certificate revoked, closing connection");
+                    return Ok(());
+                }
+            }
         }
 
         Ok(())
     }
 }
+
+/// Completes once a reload of the TLS configuration revokes `peer`, the
+/// certificate the client authenticated with. Never completes for clients
+/// without one.
+async fn revoked(reloaded: &mut watch::Receiver<()>, tls: &Tls, peer: Option<&Certificate>) {
+    // `Tls` holds the sender, so it is never dropped while it is in use
+    while reloaded.changed().await.is_ok() {
+        if peer.is_some_and(|cert| tls.is_revoked(cert)) {
+            return;
+        }
+    }
+    std::future::pending().await
+}
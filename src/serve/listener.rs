@@ -1,23 +1,35 @@
+use super::{peer_names, Reloader, Tls};
 use crate::db::DbDropGuard;
 use crate::shutdown::Shutdown;
 use crate::window::WindowCommand;
 use crate::Connection;
 
+use futures::future::select_all;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
 
 use tokio::sync::{broadcast, mpsc, Semaphore};
-use tokio::time::{self, Duration};
-use tokio_rustls::{server::TlsStream, TlsAcceptor};
-use tracing::{error, info};
+use tokio::time::{self, Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Time a client has to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Server listener state. Created in the `run` call. It includes a `run` method
 /// which performs the TCP listening and initialization of per-connection state.
 pub struct Listener {
     pub db_holder: DbDropGuard,
-    /// TCP listener supplied by the `run` caller.
-    pub listener: TcpListener,
-    pub acceptor: TlsAcceptor,
+    /// TCP listeners supplied by the `run` caller, one per bind address.
+    /// Connections are accepted on all of them concurrently.
+    pub listeners: Vec<TcpListener>,
+    /// Accepts TLS connections and tells which certificates are revoked
+    pub tls: Arc<Tls>,
+    /// Applies the config file again on `RELOAD CONFIG`, including the
+    /// roles of clients
+    pub reloader: Arc<Reloader>,
+    /// Image directory served by the window
+    pub path: PathBuf,
     pub limit_connections: Arc<Semaphore>,
 
     pub notify_shutdown: broadcast::Sender<()>,
@@ -27,7 +39,12 @@ pub struct Listener {
 
 impl Listener {
     pub async fn run(&mut self) -> crate::Result<()> {
-        info!("accepting inbound connections");
+        for listener in &self.listeners {
+            if let Ok(addr) = listener.local_addr() {
+                info!(%addr, "accepting inbound connections");
+            }
+        }
+        let mut backoff = vec![Backoff::default(); self.listeners.len()];
         loop {
             // Wait for a permit to become available
             //
@@ -47,85 +64,141 @@ impl Listener {
             // Accept a new socket. This will attempt to perform error handling.
             // The `accept` method internally attempts to recover errors, so an
             // error here is non-recoverable.
-            let socket = self.accept().await?;
-
-            // Create the necessary per-connection handler state.
-            let mut handler = super::Handler {
-                // Get a handle to the shared database.
-                db: self.db_holder.db(),
-                win_cmd_tx: self.win_cmd_tx.clone(),
-
-                // Initialize the connection state. This allocates read/write
-                // buffers to perform frame parsing.
-                connection: Connection::new(socket.into()),
-
-                shutdown: Shutdown::new(self.notify_shutdown.subscribe()),
+            let socket = self.accept(&mut backoff).await?;
 
-                // Notifies the receiver half once all clones are
-                // dropped.
-                _shutdown_complete: self.shutdown_complete_tx.clone(),
-            };
+            let acceptor = self.tls.acceptor();
+            let reloader = self.reloader.clone();
+            // Create the necessary per-connection handler state, all but
+            // the connection which only exists once the TLS handshake is
+            // done.
+            let db = self.db_holder.db();
+            let tls = self.tls.clone();
+            let reloaded = self.tls.subscribe();
+            let path = self.path.clone();
+            let win_cmd_tx = self.win_cmd_tx.clone();
+            let shutdown = Shutdown::new(self.notify_shutdown.subscribe());
+            // Notifies the receiver half once all clones are dropped.
+            let shutdown_complete = self.shutdown_complete_tx.clone();
 
             // Spawn a new task to process the connections. Tokio tasks are like
             // asynchronous green threads and are executed concurrently.
             tokio::spawn(async move {
+                // The handshake runs here rather than in the accept loop, so
+                // a slow or rejected client, e.g. one with a revoked
+                // certificate, does not hold up the others.
+                let socket = match time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(socket)).await {
+                    Ok(Ok(socket)) => socket,
+                    Ok(Err(err)) => return info!(cause = %err, "TLS handshake failed"),
+                    Err(_) => return info!("TLS handshake timed out"),
+                };
+
+                // The client is identified by the certificate it authenticated
+                // with, which decides what it may do.
+                let peer = socket
+                    .get_ref()
+                    .1
+                    .peer_certificates()
+                    .and_then(|certs| certs.first())
+                    .cloned();
+                let names = peer.as_ref().map(peer_names).unwrap_or_default();
+                let role = reloader.config().acl.role(&names);
+                info!(?names, %role, "client connected");
+
+                let mut handler = super::Handler {
+                    // Get a handle to the shared database.
+                    db,
+                    names,
+                    peer,
+                    tls,
+                    reloader,
+                    reloaded,
+                    path,
+                    win_cmd_tx,
+
+                    // Initialize the connection state. This allocates read/write
+                    // buffers to perform frame parsing.
+                    connection: Connection::new(socket.into()),
+
+                    shutdown,
+
+                    _shutdown_complete: shutdown_complete,
+                };
+
                 // Process the connection. If an error is encountered, log it.
+                let client = handler.db.connected();
                 if let Err(err) = handler.run().await {
                     error!(cause = ?err, "connection error");
                 }
                 // Move the permit into the task and drop it after completion.
                 // This returns the permit back to the semaphore.
                 drop(permit);
+                drop(client);
             });
         }
     }
 
-    /// Accept an inbound connection.
+    /// Accept an inbound TCP connection on any of the listeners.
     ///
-    /// TCP connection errors and TLS acceptance errors are handled by
-    /// backing off and retrying. An exponential backoff strategy is
-    /// used. After the first failure, the task waits for 1 second.
-    /// After the second failure, the task waits for 2 seconds. Each
-    /// subsequent failure doubles the wait time. If accepting fails
-    /// on the 6th try after waiting for 64 seconds, then this
-    /// function returns with an error. The nested structure means
-    /// there are two sequential and identical backoff procdures, the
-    /// first for the TCP connection and the second for TLS.
-    async fn accept(&mut self) -> crate::Result<TlsStream<TcpStream>> {
-        let mut backoff = 1;
-
-        // Try to accept a few times
+    /// TCP connection errors are handled by backing off and retrying,
+    /// separately for each listener so an error on one bind address does
+    /// not pause the others. An exponential backoff strategy is used.
+    /// After the first failure, the listener waits for 1 second. After
+    /// the second failure, it waits for 2 seconds. Each subsequent
+    /// failure doubles the wait time. If accepting fails on the 6th try
+    /// after waiting for 64 seconds, then this function returns with an
+    /// error. A successful accept resets the backoff of its listener.
+    async fn accept(&self, backoff: &mut [Backoff]) -> crate::Result<TcpStream> {
         loop {
-            // Perform the TCP accept operation. If a socket is successfully
-            // accepted, it to TLS. Otherwise, save the error.
-            match self.listener.accept().await {
-                Ok((socket, _)) => {
-                    // Perform the TLS accept operation. If a socket is successfully
-                    // accepted, return it. Otherwise, save the error.
-                    match self.acceptor.accept(socket).await {
-                        Ok(socket) => {
-                            return Ok(socket);
-                        }
-                        Err(err) => {
-                            if backoff > 64 {
-                                // Accept has failed too many times. Return the error.
-                                return Err(err.into());
-                            }
+            let accepts = self
+                .listeners
+                .iter()
+                .zip(backoff.iter())
+                .map(|(listener, b)| {
+                    let until = b.until;
+                    Box::pin(async move {
+                        // Pause accepting until the back off period elapses.
+                        if let Some(until) = until {
+                            time::sleep_until(until).await;
                         }
-                    };
+                        listener.accept().await
+                    })
+                });
+            let (res, index, _) = select_all(accepts).await;
+            let b = &mut backoff[index];
+            match res {
+                Ok((socket, _)) => {
+                    *b = Backoff::default();
+                    return Ok(socket);
                 }
                 Err(err) => {
-                    if backoff > 64 {
+                    if b.seconds > 64 {
                         // Accept has failed too many times. Return the error.
                         return Err(err.into());
                     }
+                    warn!(cause = %err, "failed to accept, retrying in {}s", b.seconds);
+                    b.until = Some(Instant::now() + Duration::from_secs(b.seconds));
+                    // Double the back off
+                    b.seconds *= 2;
                 }
-            };
-            // Pause execution until the back off period elapses.
-            time::sleep(Duration::from_secs(backoff)).await;
+            }
+        }
+    }
+}
+
+/// Back off state of one listener after failed accepts
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    /// wait after the next failure
+    seconds: u64,
+    /// accepting is paused until then
+    until: Option<Instant>,
+}
 
-            // Double the back off
-            backoff *= 2;
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            seconds: 1,
+            until: None,
         }
     }
 }
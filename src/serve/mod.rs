@@ -1,8 +1,14 @@
+mod acl;
+pub use acl::{peer_names, Acl, Role};
+
 mod config;
-pub use config::Config;
+pub use config::{bind_addrs, Config};
 
 mod tls;
-pub use tls::get_acceptor;
+pub use tls::{get_acceptor, Tls};
+
+mod reloader;
+pub use reloader::Reloader;
 
 mod handler;
 use handler::Handler;
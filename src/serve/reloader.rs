@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+#[cfg(feature = "server")]
+use notify::RecommendedWatcher;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+#[cfg(feature = "server")]
+use tracing::error;
+use tracing::{info, warn};
+
+use super::{Config, Tls};
+use crate::window::{SortOrder, WindowCommand};
+
+/// Re-reads the config file and applies it to the running server, on
+/// SIGHUP or `RELOAD CONFIG`.
+#[derive(Debug)]
+pub struct Reloader {
+    /// config file the server was started with
+    path: PathBuf,
+    /// settings currently applied
+    config: Mutex<Arc<Config>>,
+    tls: Arc<Tls>,
+    win_cmd_tx: Sender<WindowCommand>,
+    /// reloads the revocation list when `crl-file` is written
+    #[cfg(feature = "server")]
+    crl_watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl Reloader {
+    pub fn new(
+        path: PathBuf,
+        config: Arc<Config>,
+        tls: Arc<Tls>,
+        win_cmd_tx: Sender<WindowCommand>,
+    ) -> Self {
+        Self {
+            path,
+            config: Mutex::new(config),
+            tls,
+            win_cmd_tx,
+            #[cfg(feature = "server")]
+            crl_watcher: Mutex::new(None),
+        }
+    }
+
+    /// Settings currently applied
+    pub fn config(&self) -> Arc<Config> {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Read the config file and apply it. Certificates are taken into use
+    /// for new connections and window settings which changed in the file
+    /// are applied right away. A config file which can not be read,
+    /// certificates which can not be loaded or window settings which can
+    /// not be applied are rejected before anything is applied, and the
+    /// server keeps running with the previous config.
+    pub async fn reload(&self) -> Result<()> {
+        let config = Config::new(&self.path)
+            .map_err(|e| anyhow!("invalid config {}: {}", self.path.display(), e))?;
+        config
+            .sort
+            .parse::<SortOrder>()
+            .map_err(|e| anyhow!("invalid sort: {}", e))?;
+
+        let current = self.config();
+        let restart = [
+            ("port", config.port != current.port),
+            ("bind", config.bind != current.bind),
+            ("path", config.path != current.path),
+        ];
+        for (name, _) in restart.iter().filter(|(_, changed)| *changed) {
+            warn!("`{}` changed, restart the server to apply it", name);
+        }
+
+        // a new revocation list must be watched from now on, the old one
+        // no longer
+        #[cfg(feature = "server")]
+        let crl_watcher = if config.crl != current.crl {
+            Some(self.new_crl_watcher(&config)?)
+        } else {
+            None
+        };
+
+        let tls = Tls::load(config.clone())?;
+
+        // the window checks its settings before applying any of them, so
+        // it is the last step which may fail
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.win_cmd_tx
+            .send(WindowCommand::Configure(config.clone(), reply_tx))
+            .await?;
+        reply_rx.await?.map_err(|e| anyhow!("{}", e))?;
+
+        self.tls.apply(tls);
+
+        #[cfg(feature = "server")]
+        if let Some(crl_watcher) = crl_watcher {
+            *self.crl_watcher.lock().unwrap() = crl_watcher;
+        }
+        *self.config.lock().unwrap() = config;
+        info!("reloaded {}", self.path.display());
+        Ok(())
+    }
+
+    /// Take a new revocation list into use as soon as the `crl-file` of
+    /// `config` is written
+    #[cfg(feature = "server")]
+    pub fn watch_crl(&self, config: &Config) -> Result<()> {
+        *self.crl_watcher.lock().unwrap() = self.new_crl_watcher(config)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "server")]
+    fn new_crl_watcher(&self, config: &Config) -> Result<Option<RecommendedWatcher>> {
+        let crl = match &config.crl {
+            Some(crl) => crl,
+            None => return Ok(None),
+        };
+        let tls = self.tls.clone();
+        let watcher = crate::watcher::watch_file(crl, move || match tls.reload(tls.config()) {
+            Ok(()) => info!("reloaded certificate revocation list"),
+            Err(e) => error!("could not reload certificate revocation list: {}", e),
+        })?;
+        Ok(Some(watcher))
+    }
+}
@@ -1,71 +1,252 @@
 use anyhow::{anyhow, Result};
-use rustls_pemfile::certs;
 use std::{
-    fs::File,
-    io::{self, BufReader},
     path::Path,
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
+use tokio::sync::watch;
 use tokio_rustls::{
-    rustls::{self, server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, ServerConfig},
+    rustls::{
+        server::AllowAnyAuthenticatedClient, server::UnparsedCertRevocationList, Certificate,
+        ServerConfig,
+    },
     TlsAcceptor,
 };
 
 use super::Config;
+use crate::pem::{load_certs, load_crls, load_key, load_roots};
 
 pub fn get_acceptor(config: Arc<Config>) -> Result<TlsAcceptor> {
     let certs = load_certs(config.cert.as_path())?;
-    let key = load_keys(config.key.as_path())?;
+    let key = load_key(config.key.as_path())?;
+    let store = load_roots(config.ca.as_path())?;
 
-    let mut store = rustls::RootCertStore::empty();
-    let f = File::open(config.ca.as_path())?;
-    let mut pem = BufReader::new(f);
-
-    for cert in rustls_pemfile::certs(&mut pem)? {
-        let cert = Certificate(cert);
-        store.add(&cert)?;
+    let mut client_auth = AllowAnyAuthenticatedClient::new(store);
+    if let Some(path) = &config.crl {
+        let crls = load_crls(path)?.into_iter().map(UnparsedCertRevocationList);
+        client_auth = client_auth
+            .with_crls(crls)
+            .map_err(|err| anyhow!("invalid revocation list {}: {:?}", path.display(), err))?;
     }
-
-    let client_auth = AllowAnyAuthenticatedClient::new(store);
     let config = ServerConfig::builder()
         .with_safe_defaults()
         .with_client_cert_verifier(client_auth.boxed())
-        .with_single_cert(certs, PrivateKey(key))
+        .with_single_cert(certs, key)
         .map_err(|err| anyhow!(err))?;
     let acceptor = TlsAcceptor::from(Arc::new(config));
     Ok(acceptor)
 }
 
-fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
-    let c = certs(&mut BufReader::new(File::open(path)?))?;
-    let certs = c.into_iter().map(Certificate).collect();
-    Ok(certs)
+/// Serial number of a revoked certificate and the issuer revoking it
+type Revoked = (Vec<u8>, Vec<u8>);
+
+/// TLS state of the server which can be reloaded while it runs, so that
+/// a new certificate revocation list takes effect without a restart.
+pub struct Tls {
+    config: RwLock<Arc<Config>>,
+    acceptor: RwLock<TlsAcceptor>,
+    /// certificates revoked by `crl-file`
+    revoked: RwLock<Vec<Revoked>>,
+    /// notified after every reload so that open connections can check
+    /// whether their certificate was revoked
+    reloaded: watch::Sender<()>,
+}
+
+/// TLS state read by [`Tls::load`], not yet taken into use
+pub struct Loaded {
+    config: Arc<Config>,
+    acceptor: TlsAcceptor,
+    revoked: Vec<Revoked>,
+}
+
+impl Tls {
+    pub fn new(config: Arc<Config>) -> Result<Self> {
+        let Loaded {
+            config,
+            acceptor,
+            revoked,
+        } = Tls::load(config)?;
+        let (reloaded, _) = watch::channel(());
+        Ok(Self {
+            config: RwLock::new(config),
+            acceptor: RwLock::new(acceptor),
+            revoked: RwLock::new(revoked),
+            reloaded,
+        })
+    }
+    /// Acceptor for new connections
+    pub fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap().clone()
+    }
+    /// Files the TLS state was last loaded from
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+    /// Read the certificates, key and revocation list named by `config`.
+    /// On error the previous ones are kept.
+    pub fn reload(&self, config: Arc<Config>) -> Result<()> {
+        self.apply(Tls::load(config)?);
+        Ok(())
+    }
+    /// Read the certificates, key and revocation list named by `config`
+    /// without taking them into use, see [`Tls::apply`]
+    pub fn load(config: Arc<Config>) -> Result<Loaded> {
+        let acceptor = get_acceptor(config.clone())?;
+        let revoked = match &config.crl {
+            Some(path) => revoked(path)?,
+            None => vec![],
+        };
+        Ok(Loaded {
+            config,
+            acceptor,
+            revoked,
+        })
+    }
+    /// Take TLS state read by [`Tls::load`] into use
+    pub fn apply(&self, loaded: Loaded) {
+        *self.config.write().unwrap() = loaded.config;
+        *self.acceptor.write().unwrap() = loaded.acceptor;
+        *self.revoked.write().unwrap() = loaded.revoked;
+        self.reloaded.send_replace(());
+    }
+    /// Receiver notified after every reload
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.reloaded.subscribe()
+    }
+    /// Check if `cert` was revoked by the current revocation list
+    pub fn is_revoked(&self, cert: &Certificate) -> bool {
+        let cert = match x509_parser::parse_x509_certificate(&cert.0) {
+            Ok((_, cert)) => cert,
+            Err(_) => return false,
+        };
+        let serial = cert.raw_serial();
+        let issuer = cert.issuer().as_raw();
+        self.revoked
+            .read()
+            .unwrap()
+            .iter()
+            .any(|(s, i)| s == serial && i == issuer)
+    }
 }
 
-fn load_keys(path: &Path) -> io::Result<Vec<u8>> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
-    let pem = rustls_pemfile::pkcs8_private_keys(&mut reader)?;
-    pem.into_iter().next().ok_or(io::ErrorKind::NotFound.into())
+impl std::fmt::Debug for Tls {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Tls")
+            .field("crl", &self.config().crl)
+            .field("revoked", &self.revoked.read().unwrap().len())
+            .finish()
+    }
 }
 
-// here for reference
-fn _identify_pem(path: &Path) -> io::Result<()> {
-    use std::iter;
-
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file);
-
-    use rustls_pemfile::{read_one, Item};
-    for item in iter::from_fn(|| read_one(&mut reader).transpose()) {
-        match item? {
-            Item::X509Certificate(cert) => println!("certificate {:?}", cert),
-            Item::Crl(crl) => println!("certificate revocation list: {:?}", crl),
-            Item::RSAKey(key) => println!("rsa pkcs1 key {:?}", key),
-            Item::PKCS8Key(key) => println!("pkcs8 key {:?}", key),
-            Item::ECKey(key) => println!("sec1 ec key {:?}", key),
-            _ => println!("unhandled item"),
+/// Certificates revoked by the lists in `path`
+fn revoked(path: &Path) -> Result<Vec<Revoked>> {
+    let mut revoked = vec![];
+    for der in load_crls(path)? {
+        let (_, crl) = x509_parser::parse_x509_crl(&der)
+            .map_err(|err| anyhow!("invalid revocation list {}: {}", path.display(), err))?;
+        let issuer = crl.issuer().as_raw().to_vec();
+        for cert in crl.iter_revoked_certificates() {
+            revoked.push((cert.raw_serial().to_vec(), issuer.clone()));
         }
     }
-    Ok(())
+    Ok(revoked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, Certificate as Issued, CertificateParams, CertificateRevocationList,
+        CertificateRevocationListParams, DnType, IsCa, KeyIdMethod, KeyUsagePurpose,
+        RevokedCertParams, SerialNumber, PKCS_ECDSA_P256_SHA256,
+    };
+    use std::fs;
+    use time::{Duration, OffsetDateTime};
+
+    /// Certificate for `name` with `serial`, to be signed by a CA
+    fn issue(name: &str, serial: u64) -> Result<Issued> {
+        let mut params = CertificateParams::new(vec![name.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.serial_number = Some(SerialNumber::from(serial));
+        Ok(Issued::from_params(params)?)
+    }
+
+    /// Write a revocation list of `ca` revoking `serials` to `path`
+    fn revoke(ca: &Issued, serials: &[u64], path: &Path) -> Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let params = CertificateRevocationListParams {
+            this_update: now,
+            next_update: now + Duration::days(1),
+            crl_number: SerialNumber::from(1),
+            issuing_distribution_point: None,
+            revoked_certs: serials
+                .iter()
+                .map(|&serial| RevokedCertParams {
+                    serial_number: SerialNumber::from(serial),
+                    revocation_time: now,
+                    reason_code: None,
+                    invalidity_date: None,
+                })
+                .collect(),
+            alg: &PKCS_ECDSA_P256_SHA256,
+            key_identifier_method: KeyIdMethod::Sha256,
+        };
+        let crl = CertificateRevocationList::from_params(params)?;
+        fs::write(path, crl.serialize_pem_with_signer(ca)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_revoked() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("viewd-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "viewd test CA");
+        let ca = Issued::from_params(params)?;
+        fs::write(dir.join("ca.pem"), ca.serialize_pem()?)?;
+        let server = issue("localhost", 1)?;
+        fs::write(
+            dir.join("server.pem"),
+            server.serialize_pem_with_signer(&ca)?,
+        )?;
+        fs::write(dir.join("server.key"), server.serialize_private_key_pem())?;
+        let phone = Certificate(issue("phone", 2)?.serialize_der_with_signer(&ca)?);
+        let laptop = Certificate(issue("laptop", 3)?.serialize_der_with_signer(&ca)?);
+
+        let crl = dir.join("crl.pem");
+        revoke(&ca, &[], &crl)?;
+        let config = dir.join("server.toml");
+        fs::write(
+            &config,
+            format!(
+                r#"
+path = "{dir}"
+ca-file = "{dir}/ca.pem"
+tls-key-file = "{dir}/server.key"
+tls-cert-file = "{dir}/server.pem"
+crl-file = "{dir}/crl.pem"
+"#,
+                dir = dir.display()
+            ),
+        )?;
+        let config = Config::new(&config)?;
+        let tls = Tls::new(config.clone())?;
+        assert!(!tls.is_revoked(&phone));
+
+        // takes effect on reload only
+        revoke(&ca, &[2], &crl)?;
+        assert!(!tls.is_revoked(&phone));
+        let reloaded = tls.subscribe();
+        tls.reload(config)?;
+        assert!(reloaded.has_changed()?);
+        assert!(tls.is_revoked(&phone));
+        assert!(!tls.is_revoked(&laptop));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
@@ -1,24 +1,27 @@
 use crate::db::DbDropGuard;
+use crate::serve::{Reloader, Tls};
 
 use crate::window::WindowCommand;
 use crate::Listener;
 
 use std::future::Future;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{broadcast, mpsc, Semaphore};
 
-use tokio_rustls::TlsAcceptor;
 use tracing::{error, info};
 
 const MAX_CONNECTIONS: usize = 250;
 
 pub(crate) async fn run(
-    listener: TcpListener,
+    listeners: Vec<TcpListener>,
     db_holder: DbDropGuard,
     win_cmd_tx: Sender<WindowCommand>,
-    acceptor: TlsAcceptor,
+    tls: Arc<Tls>,
+    reloader: Arc<Reloader>,
+    path: PathBuf,
     shutdown: impl Future,
 ) {
     let (notify_shutdown, _) = broadcast::channel(1);
@@ -26,9 +29,11 @@ pub(crate) async fn run(
 
     // Initialize the listener state
     let mut server = Listener {
-        listener,
+        listeners,
         db_holder,
-        acceptor,
+        tls,
+        reloader,
+        path,
         limit_connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
         notify_shutdown,
         shutdown_complete_tx,
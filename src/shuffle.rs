@@ -0,0 +1,77 @@
+//! Small seedable random number generator used to shuffle images.
+//!
+//! Shuffling does not need to be cryptographically secure, so this avoids
+//! pulling in a dependency. The generator is xorshift64*.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Mixed into seeds, so small ones do not start with a mostly zero state
+const SEED_MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    /// Create a generator which always yields the same sequence for `seed`.
+    pub(crate) fn new(seed: u64) -> Self {
+        // the state must never be zero, xorshift would only yield zeros
+        match seed ^ SEED_MIX {
+            0 => Self(1),
+            state => Self(state),
+        }
+    }
+
+    /// Create a generator seeded from the current time.
+    pub(crate) fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Random number in `0..n`. `n` must not be zero.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Fisher-Yates shuffle of `items`
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shuffle_is_seeded_permutation() {
+        let mut a: Vec<u32> = (0..50).collect();
+        let mut b = a.clone();
+        Rng::new(42).shuffle(&mut a);
+        Rng::new(42).shuffle(&mut b);
+        assert_eq!(a, b);
+        assert_ne!(a, (0..50).collect::<Vec<_>>());
+        a.sort();
+        assert_eq!(a, (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_seed_mixed_to_zero() {
+        let mut rng = Rng::new(SEED_MIX);
+        assert!((0..10).any(|_| rng.next_u64() != 0));
+        let mut a: Vec<u32> = (0..50).collect();
+        rng.shuffle(&mut a);
+        assert_ne!(a, (0..50).collect::<Vec<_>>());
+    }
+}
@@ -1,9 +1,10 @@
+use bytes::Bytes;
 use crossterm::terminal::{Clear, ClearType, SetTitle};
+use futures::stream::LocalBoxStream;
 use futures::{future::FutureExt, select, StreamExt};
-use futures_timer::Delay;
+use std::collections::HashMap;
 use std::io;
 
-use std::time::Duration;
 use std::{fmt, str};
 use tracing::debug;
 
@@ -15,7 +16,10 @@ use crossterm::{
     style::{self, Stylize},
 };
 
-use crate::window::DISPLAY_PATH;
+use crate::clients::{Message, Subscriber};
+use crate::window::{
+    Listing, Pan, ScaleMode, Zoom, DISPLAY_PATH, FULLSCREEN, PAGEANT, ROTATION, SCALE, ZOOM,
+};
 use crate::Client;
 
 /// Enumeration of commands to send to Server
@@ -27,6 +31,9 @@ enum ServerCommand {
     Prev,
     Next,
     Pageant,
+    Scale(ScaleMode),
+    Zoom(Zoom),
+    Pan(Pan),
 }
 
 impl fmt::Display for ServerCommand {
@@ -37,6 +44,21 @@ impl fmt::Display for ServerCommand {
             Self::Pageant => write!(f, "pageant"),
             Self::Next => write!(f, "next"),
             Self::Prev => write!(f, "prev"),
+            Self::Scale(_) => write!(f, "scale"),
+            Self::Zoom(_) => write!(f, "zoom"),
+            Self::Pan(_) => write!(f, "pan"),
+        }
+    }
+}
+
+impl ServerCommand {
+    /// Value sent along with the command, empty for toggles
+    fn value(&self) -> Bytes {
+        match self {
+            Self::Scale(mode) => mode.to_string().into(),
+            Self::Zoom(zoom) => zoom.to_string().into(),
+            Self::Pan(pan) => pan.to_string().into(),
+            _ => Bytes::new(),
         }
     }
 }
@@ -44,10 +66,14 @@ impl fmt::Display for ServerCommand {
 struct Shutdown(bool);
 /// Struct to hold Terminal UI
 pub struct Tui {
-    /// Time to wait between ui updates
-    wait: Duration,
     /// viewd Client
     client: Client,
+    /// State changes pushed by the server
+    messages: LocalBoxStream<'static, crate::Result<Message>>,
+    /// Last known value of each server key
+    status: HashMap<String, String>,
+    /// Images around the current one, shown when toggled with Tab
+    listing: Option<Listing>,
     /// EventStream reader
     reader: EventStream,
     /// Window title,
@@ -55,29 +81,91 @@ pub struct Tui {
 }
 
 impl Tui {
-    pub fn new(client: Client) -> Self {
+    /// Create the Terminal UI. `client` issues commands, while
+    /// `subscriber` keeps the displayed state up to date. They need
+    /// separate connections as a subscribed connection only receives.
+    pub fn new(client: Client, subscriber: Subscriber) -> Self {
         let reader = EventStream::new();
-        let wait = Duration::from_millis(1_000);
+        let messages = subscriber.into_stream().boxed_local();
         let title = String::from("viewd-tui");
         Self {
-            wait,
             client,
+            messages,
+            status: HashMap::new(),
+            listing: None,
             reader,
             title,
         }
     }
     /// Wrap the TCP command setter
     async fn exec(&mut self, cmd: ServerCommand) -> crate::Result<()> {
-        self.client.set(&cmd.to_string(), vec![].into()).await
+        self.client.set(&cmd.to_string(), cmd.value()).await
+    }
+    /// Scale mode last reported by the server
+    fn scale(&self) -> ScaleMode {
+        self.status
+            .get(SCALE)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default()
+    }
+    /// Record a state change and redraw
+    async fn update(&mut self, message: Message) -> crate::Result<()> {
+        let value = String::from_utf8_lossy(&message.value).into_owned();
+        if message.key == DISPLAY_PATH && self.listing.is_some() {
+            self.refresh_listing().await?;
+        }
+        self.status.insert(message.key, value);
+        self.draw()?;
+        Ok(())
+    }
+    /// Show or hide the images surrounding the current one
+    async fn toggle_listing(&mut self) -> crate::Result<()> {
+        if self.listing.take().is_none() {
+            self.refresh_listing().await?;
+        }
+        self.draw()?;
+        Ok(())
+    }
+    /// Fetch the page of images centered on the current one
+    async fn refresh_listing(&mut self) -> crate::Result<()> {
+        const CONTEXT: usize = 5;
+        // the current index is part of every listing
+        let current = self.client.list(0, 0).await?.current;
+        let offset = current.saturating_sub(CONTEXT);
+        let listing = self.client.list(offset, 2 * CONTEXT + 1).await?;
+        self.listing = Some(listing);
+        Ok(())
     }
-    /// Update currently displayed image name
-    fn update(&self, s: &str) -> io::Result<()> {
+    /// Draw currently displayed image name and window state
+    fn draw(&self) -> io::Result<()> {
+        let get = |key: &str| self.status.get(key).map_or("-", |s| s.as_str());
+        let state = format!(
+            "fullscreen: {}  rotation: {}  pageant: {}  scale: {}  zoom: {}",
+            get(FULLSCREEN),
+            get(ROTATION),
+            get(PAGEANT),
+            get(SCALE),
+            get(ZOOM)
+        );
         execute!(
             io::stdout(),
             Clear(ClearType::All),
             MoveTo(0, 0),
-            style::Print(s.magenta())
+            style::Print(get(DISPLAY_PATH).magenta()),
+            MoveTo(0, 1),
+            style::Print(state.dim())
         )?;
+        if let Some(listing) = &self.listing {
+            for (row, (index, path)) in listing.entries.iter().enumerate() {
+                let line = format!("{:>6} {}", index, path.display());
+                let line = if *index == listing.current {
+                    line.bold()
+                } else {
+                    line.reset()
+                };
+                execute!(io::stdout(), MoveTo(0, row as u16 + 3), style::Print(line))?;
+            }
+        }
         Ok(())
     }
     /// Send Tls Stream the shutdown signal
@@ -102,6 +190,20 @@ impl Tui {
                 }
                 KeyCode::Char('f') => self.exec(ServerCommand::Fullscreen).await?,
                 KeyCode::Char('r') => self.exec(ServerCommand::Rotate).await?,
+                KeyCode::Char('s') => {
+                    let mode = self.scale().next();
+                    self.exec(ServerCommand::Scale(mode)).await?
+                }
+                KeyCode::Char('+') | KeyCode::Char('=') => {
+                    self.exec(ServerCommand::Zoom(Zoom::In)).await?
+                }
+                KeyCode::Char('-') => self.exec(ServerCommand::Zoom(Zoom::Out)).await?,
+                KeyCode::Char('0') => self.exec(ServerCommand::Zoom(Zoom::Reset)).await?,
+                KeyCode::Char('h') => self.exec(ServerCommand::Pan(Pan::Left)).await?,
+                KeyCode::Char('j') => self.exec(ServerCommand::Pan(Pan::Down)).await?,
+                KeyCode::Char('k') => self.exec(ServerCommand::Pan(Pan::Up)).await?,
+                KeyCode::Char('l') => self.exec(ServerCommand::Pan(Pan::Right)).await?,
+                KeyCode::Tab => self.toggle_listing().await?,
                 KeyCode::Right => self.exec(ServerCommand::Next).await?,
                 KeyCode::Left => self.exec(ServerCommand::Prev).await?,
                 KeyCode::Char(' ') | KeyCode::Char('p') => {
@@ -113,38 +215,36 @@ impl Tui {
         Ok(Shutdown(false))
     }
     /// Event Loop to map keyboard events to TCP commands. Image
-    /// currently displayed in the terminal will also be updated
-    /// every `delay`.
-    #[rustfmt::skip]
+    /// currently displayed in the terminal is updated whenever the
+    /// server pushes a change.
     pub async fn handle_events(&mut self) -> crate::Result<()> {
         loop {
-            let mut delay = Delay::new(self.wait).fuse();
+            let mut message = self.messages.next().fuse();
             let mut event = self.reader.next().fuse();
 
-            // select on `get`s to server and polling for keyboard input
+            // select on messages from server and polling for keyboard input
             select! {
-            _ = delay => {
-                    let s = DISPLAY_PATH;
-                    if let Some(value) = self.client.get(s).await? {
-                        if let Ok(s) = str::from_utf8(&value) {
-			    self.update(s)?
-                         }
-                     }
-                 }
-                 maybe_event = event => {
-                     match maybe_event {
-                         Some(Err(e)) => debug!("Error: {:?}\r", e),
-                         None => break Ok(()),
-                         Some(Ok(event)) => {
-                             // handle Command Events
-			     // if Shutdown was set to true, break
-			     if self.handle_keycode(event).await?.0 {
-				 break Ok(());
-			     }
-                         }
-                     }
-		 }
-	    }
+                maybe_message = message => {
+                    match maybe_message {
+                        Some(message) => self.update(message?).await?,
+                        // server ended the subscription
+                        None => break Ok(()),
+                    }
+                }
+                maybe_event = event => {
+                    match maybe_event {
+                        Some(Err(e)) => debug!("Error: {:?}\r", e),
+                        None => break Ok(()),
+                        Some(Ok(event)) => {
+                            // handle Command Events
+                            // if Shutdown was set to true, break
+                            if self.handle_keycode(event).await?.0 {
+                                break Ok(());
+                            }
+                        }
+                    }
+                }
+            }
         }
     }
     /// Set terminal window title and clear the terminal
@@ -0,0 +1,138 @@
+//! Watch the image directory and forward changes to the window.
+//!
+//! Changes are reported by inotify on Linux. Hidden files are ignored, in
+//! particular the partial files written while an upload is in progress.
+//! Linking or renaming one into place is reported as a creation.
+
+use anyhow::{anyhow, Result};
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, warn};
+
+use crate::window::{FileEvent, WindowCommand};
+
+/// Start watching `root` recursively. Changes are sent to the window
+/// over `tx` for as long as the returned watcher is alive.
+pub(crate) fn watch(root: &Path, tx: Sender<WindowCommand>) -> Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => return warn!("watch error: {}", e),
+        };
+        debug!(?event);
+        if let Some(change) = file_event(event) {
+            // the receiving end is gone once the window has shut down
+            let _ = tx.blocking_send(WindowCommand::File(change));
+        }
+    })?;
+    watcher.watch(root, RecursiveMode::Recursive)?;
+    Ok(watcher)
+}
+
+/// Watch the file at `path` and call `changed` whenever it is written or
+/// replaced, for as long as the returned watcher is alive. The directory
+/// holding the file is watched, as editors usually replace files instead
+/// of writing to them.
+pub(crate) fn watch_file(
+    path: &Path,
+    changed: impl Fn() + Send + 'static,
+) -> Result<RecommendedWatcher> {
+    let path = path.canonicalize()?;
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", path.display()))?
+        .to_path_buf();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => return warn!("watch error: {}", e),
+        };
+        let written = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_))
+        );
+        if written && event.paths.contains(&path) {
+            changed();
+        }
+    })?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+/// Translate a notify event into a change to the collection
+fn file_event(event: Event) -> Option<FileEvent> {
+    let mut paths = event.paths.into_iter();
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            visible(paths.next()?).map(FileEvent::Created)
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            visible(paths.next()?).map(FileEvent::Removed)
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            let from = visible(paths.next()?);
+            let to = visible(paths.next()?);
+            match (from, to) {
+                (Some(from), Some(to)) => Some(FileEvent::Renamed(from, to)),
+                (None, Some(to)) => Some(FileEvent::Created(to)),
+                (Some(from), None) => Some(FileEvent::Removed(from)),
+                (None, None) => None,
+            }
+        }
+        // the backend could not tell which side of the rename this is
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            let path = visible(paths.next()?)?;
+            if path.exists() {
+                Some(FileEvent::Created(path))
+            } else {
+                Some(FileEvent::Removed(path))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `path` unless its file name starts with a dot
+fn visible(path: PathBuf) -> Option<PathBuf> {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    (!hidden).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+
+    #[test]
+    fn test_file_event() {
+        let event = Event::new(EventKind::Create(CreateKind::File)).add_path("a/b.jpg".into());
+        assert_eq!(
+            file_event(event),
+            Some(FileEvent::Created("a/b.jpg".into()))
+        );
+
+        let event = Event::new(EventKind::Remove(RemoveKind::File)).add_path("a/.b.jpg".into());
+        assert_eq!(file_event(event), None);
+
+        // an upload being moved into place
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path("up/.b.jpg.part".into())
+            .add_path("up/b.jpg".into());
+        assert_eq!(
+            file_event(event),
+            Some(FileEvent::Created("up/b.jpg".into()))
+        );
+
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path("a.jpg".into())
+            .add_path("b.jpg".into());
+        assert_eq!(
+            file_event(event),
+            Some(FileEvent::Renamed("a.jpg".into(), "b.jpg".into()))
+        );
+    }
+}
@@ -1,12 +1,769 @@
 use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::glob::glob_match;
+use crate::serve::Config;
+use std::sync::Arc;
+use tokio::sync::oneshot;
 
 /// String used as a db key for the image currently displayed
 pub const DISPLAY_PATH: &str = "display_path";
+/// Db key holding the fullscreen mode, `on` or `off`
+pub const FULLSCREEN: &str = "fullscreen";
+/// Db key holding the rotation of the image in degrees
+pub const ROTATION: &str = "rotation";
+/// Db key holding the pageant settings, e.g. `on 1000 forward sequential`.
+/// The value can be passed back to `SET pageant`.
+pub const PAGEANT: &str = "pageant";
+/// Db key holding the current [`ScaleMode`]
+pub const SCALE: &str = "scale";
+/// Db key holding the zoom factor
+pub const ZOOM: &str = "zoom";
+/// Db key holding the current [`Filter`]
+pub const FILTER: &str = "filter";
+/// Db key holding the current [`SortOrder`]
+pub const SORT: &str = "sort";
+
+/// How images are laid out in the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde_derive::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScaleMode {
+    /// Show the whole image, letterboxing the remaining space
+    #[default]
+    Fit,
+    /// Cover the whole window, cropping what does not fit
+    Fill,
+    /// One image pixel per window pixel
+    Actual,
+    /// Scale the image to the width of the window
+    FitWidth,
+}
+
+impl ScaleMode {
+    /// All modes, in the order they are cycled through.
+    pub const ALL: [ScaleMode; 4] = [
+        ScaleMode::Fit,
+        ScaleMode::Fill,
+        ScaleMode::Actual,
+        ScaleMode::FitWidth,
+    ];
+
+    /// Returns the mode following `self`.
+    pub fn next(self) -> ScaleMode {
+        let i = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl FromStr for ScaleMode {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<ScaleMode> {
+        let mode = match s.to_lowercase().as_str() {
+            "fit" => ScaleMode::Fit,
+            "fill" => ScaleMode::Fill,
+            "actual" => ScaleMode::Actual,
+            "fit-width" => ScaleMode::FitWidth,
+            _ => return Err(format!("invalid scale mode '{}'", s).into()),
+        };
+        Ok(mode)
+    }
+}
+
+impl fmt::Display for ScaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Fit => write!(f, "fit"),
+            Self::Fill => write!(f, "fill"),
+            Self::Actual => write!(f, "actual"),
+            Self::FitWidth => write!(f, "fit-width"),
+        }
+    }
+}
+
+/// Order of the images in the collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    /// File name, then path for files with the same name
+    #[default]
+    Name,
+    /// Path relative to the image directory
+    Path,
+    /// File name with runs of digits compared by value, so `img2` comes
+    /// before `img10`
+    Natural,
+    /// Modification time, oldest first
+    Modified,
+    /// File size, smallest first
+    Size,
+    /// EXIF capture date, oldest first. Files without one come last,
+    /// by modification time.
+    Taken,
+    /// Random order. The same seed always gives the same order.
+    Shuffle(u64),
+}
+
+impl FromStr for SortOrder {
+    type Err = crate::Error;
+
+    /// Parses an order name. `shuffle` takes an optional seed, as in
+    /// `shuffle:42`, and picks one at random otherwise.
+    fn from_str(s: &str) -> crate::Result<SortOrder> {
+        let order = match s.to_lowercase().as_str() {
+            "name" => SortOrder::Name,
+            "path" => SortOrder::Path,
+            "natural" => SortOrder::Natural,
+            "modified" | "mtime" => SortOrder::Modified,
+            "size" => SortOrder::Size,
+            "taken" | "exif" => SortOrder::Taken,
+            "shuffle" => SortOrder::Shuffle(crate::shuffle::Rng::from_time().next_u64()),
+            other => match other.strip_prefix("shuffle:").map(str::parse) {
+                Some(Ok(seed)) => SortOrder::Shuffle(seed),
+                _ => return Err(format!("invalid sort order '{}'", s).into()),
+            },
+        };
+        Ok(order)
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Name => write!(f, "name"),
+            Self::Path => write!(f, "path"),
+            Self::Natural => write!(f, "natural"),
+            Self::Modified => write!(f, "modified"),
+            Self::Size => write!(f, "size"),
+            Self::Taken => write!(f, "taken"),
+            Self::Shuffle(seed) => write!(f, "shuffle:{}", seed),
+        }
+    }
+}
+
+impl serde::Serialize for SortOrder {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Change of the zoom factor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zoom {
+    In,
+    Out,
+    Reset,
+}
+
+impl FromStr for Zoom {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Zoom> {
+        let zoom = match s.to_lowercase().as_str() {
+            "in" | "+" => Zoom::In,
+            "out" | "-" => Zoom::Out,
+            "reset" | "0" => Zoom::Reset,
+            _ => return Err(format!("invalid zoom '{}'", s).into()),
+        };
+        Ok(zoom)
+    }
+}
+
+impl fmt::Display for Zoom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::In => write!(f, "in"),
+            Self::Out => write!(f, "out"),
+            Self::Reset => write!(f, "reset"),
+        }
+    }
+}
+
+/// Change to the playback of an animated image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationControl {
+    Pause,
+    Resume,
+    Toggle,
+    /// Show the next frame, pausing the animation
+    Next,
+    /// Show the previous frame, pausing the animation
+    Prev,
+}
+
+impl FromStr for AnimationControl {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<AnimationControl> {
+        let control = match s.to_lowercase().as_str() {
+            "pause" | "stop" => AnimationControl::Pause,
+            "resume" | "play" => AnimationControl::Resume,
+            "toggle" | "" => AnimationControl::Toggle,
+            "next" => AnimationControl::Next,
+            "prev" | "previous" => AnimationControl::Prev,
+            _ => return Err(format!("invalid animation control '{}'", s).into()),
+        };
+        Ok(control)
+    }
+}
+
+impl fmt::Display for AnimationControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pause => write!(f, "pause"),
+            Self::Resume => write!(f, "resume"),
+            Self::Toggle => write!(f, "toggle"),
+            Self::Next => write!(f, "next"),
+            Self::Prev => write!(f, "prev"),
+        }
+    }
+}
+
+/// Movement of the visible part of a zoomed image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pan {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Center the view on a point of the image, given as fractions of
+    /// its width and height
+    To(f64, f64),
+}
+
+impl FromStr for Pan {
+    type Err = crate::Error;
+
+    /// Parses a direction or an `x,y` point.
+    fn from_str(s: &str) -> crate::Result<Pan> {
+        let pan = match s.to_lowercase().as_str() {
+            "up" => Pan::Up,
+            "down" => Pan::Down,
+            "left" => Pan::Left,
+            "right" => Pan::Right,
+            point => {
+                let err = || format!("invalid pan '{}'", s);
+                let (x, y) = point.split_once(',').ok_or_else(err)?;
+                let x: f64 = x.trim().parse().map_err(|_| err())?;
+                let y: f64 = y.trim().parse().map_err(|_| err())?;
+                if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+                    return Err(err().into());
+                }
+                Pan::To(x, y)
+            }
+        };
+        Ok(pan)
+    }
+}
+
+impl fmt::Display for Pan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Up => write!(f, "up"),
+            Self::Down => write!(f, "down"),
+            Self::Left => write!(f, "left"),
+            Self::Right => write!(f, "right"),
+            Self::To(x, y) => write!(f, "{},{}", x, y),
+        }
+    }
+}
+
+/// Direction pageant mode moves through the collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde_derive::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    #[default]
+    Forward,
+    Reverse,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Forward => write!(f, "forward"),
+            Self::Reverse => write!(f, "reverse"),
+        }
+    }
+}
+
+/// Order in which pageant mode shows images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde_derive::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    /// One after the other, in the direction set
+    #[default]
+    Sequential,
+    /// Every image once in random order before any repeats
+    Shuffle,
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sequential => write!(f, "sequential"),
+            Self::Shuffle => write!(f, "shuffle"),
+        }
+    }
+}
+
+/// Change to pageant mode requested with `SET pageant`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageantControl {
+    Start,
+    Stop,
+    Toggle,
+    /// Milliseconds each image is shown
+    Interval(u64),
+    Direction(Direction),
+    Order(Order),
+}
+
+impl PageantControl {
+    /// Parse a whitespace separated list of controls, such as
+    /// `start 2000 reverse`. An empty value toggles pageant mode.
+    pub fn parse_all(s: &str) -> crate::Result<Vec<PageantControl>> {
+        let controls = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<crate::Result<Vec<_>>>()?;
+        if controls.is_empty() {
+            return Ok(vec![PageantControl::Toggle]);
+        }
+        Ok(controls)
+    }
+}
+
+impl FromStr for PageantControl {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<PageantControl> {
+        let control = match s.to_lowercase().as_str() {
+            "start" | "on" => PageantControl::Start,
+            "stop" | "off" => PageantControl::Stop,
+            "toggle" => PageantControl::Toggle,
+            "forward" => PageantControl::Direction(Direction::Forward),
+            "reverse" => PageantControl::Direction(Direction::Reverse),
+            "sequential" => PageantControl::Order(Order::Sequential),
+            "shuffle" => PageantControl::Order(Order::Shuffle),
+            ms => match ms.trim_end_matches("ms").parse::<u64>() {
+                Ok(ms) if ms > 0 => PageantControl::Interval(ms),
+                _ => return Err(format!("invalid pageant setting '{}'", s).into()),
+            },
+        };
+        Ok(control)
+    }
+}
+
+/// Rules deciding which files under the image directory are part of the
+/// collection. Paths are matched relative to the image directory.
+#[derive(Debug, Clone, PartialEq, Eq, serde_derive::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Filter {
+    /// Extensions to accept, case insensitive. Empty accepts all.
+    pub extensions: Vec<String>,
+    /// Globs a file must match one of. Empty accepts all.
+    pub include: Vec<String>,
+    /// Globs excluding matching files and directories
+    pub exclude: Vec<String>,
+    /// Include files and directories whose name starts with a dot
+    pub hidden: bool,
+    /// Deepest level of directories to descend into, 1 being the files
+    /// directly under the image directory
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    /// Smallest file size in bytes
+    pub min_size: Option<u64>,
+    /// Largest file size in bytes
+    pub max_size: Option<u64>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self {
+            extensions: vec![],
+            include: vec![],
+            exclude: vec![],
+            // as before filters existed
+            hidden: true,
+            max_depth: None,
+            follow_symlinks: false,
+            min_size: None,
+            max_size: None,
+        }
+    }
+}
+
+impl Filter {
+    /// Check if a directory should be descended into. `relative` is the
+    /// path of the directory relative to the image directory.
+    pub fn accepts_dir(&self, relative: &Path) -> bool {
+        let depth = relative.components().count();
+        self.visible(relative)
+            && self.max_depth.is_none_or(|max| depth < max)
+            && !self.excluded(relative)
+    }
+
+    /// Check if a file of `size` bytes is part of the collection.
+    /// `relative` is its path relative to the image directory.
+    pub fn accepts_file(&self, relative: &Path, size: u64) -> bool {
+        let depth = relative.components().count();
+        let text = relative.to_string_lossy();
+        let extension = relative
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        self.visible(relative)
+            && self.max_depth.is_none_or(|max| depth <= max)
+            && (self.extensions.is_empty()
+                || extension
+                    .is_some_and(|e| self.extensions.iter().any(|x| x.eq_ignore_ascii_case(&e))))
+            && (self.include.is_empty() || self.include.iter().any(|g| glob_match(g, &text)))
+            && !self.excluded(relative)
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    fn visible(&self, relative: &Path) -> bool {
+        self.hidden
+            || !relative
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+    }
+
+    fn excluded(&self, relative: &Path) -> bool {
+        let text = relative.to_string_lossy();
+        self.exclude.iter().any(|g| glob_match(g, &text))
+    }
+
+    /// Change a setting given as `name=value`, see [`Display`](fmt::Display)
+    /// for the names. Lists are comma separated and `none` lifts a limit.
+    pub fn set(&mut self, setting: &str) -> crate::Result<()> {
+        let err = || format!("invalid filter setting '{}'", setting);
+        let (name, value) = setting.split_once('=').ok_or_else(err)?;
+        let list = || -> crate::Result<Vec<String>> {
+            let list: Vec<String> = value
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect();
+            list.iter().try_for_each(|pattern| check_pattern(pattern))?;
+            Ok(list)
+        };
+        let on = || match value {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(err()),
+        };
+        let limit = || match value {
+            "none" | "" => Ok(None),
+            n => n.parse().map(Some).map_err(|_| err()),
+        };
+        match name {
+            "ext" | "extensions" => {
+                self.extensions = list()?
+                    .iter()
+                    .map(|e| e.trim_start_matches('.').to_lowercase())
+                    .collect()
+            }
+            "include" => self.include = list()?,
+            "exclude" => self.exclude = list()?,
+            "hidden" => self.hidden = on()?,
+            "max-depth" => self.max_depth = limit()?,
+            "follow-symlinks" => self.follow_symlinks = on()?,
+            "min-size" => self.min_size = parse_size(value).map_err(|_| err())?,
+            "max-size" => self.max_size = parse_size(value).map_err(|_| err())?,
+            _ => return Err(err().into()),
+        }
+        Ok(())
+    }
+
+    /// Check that the extensions and globs, e.g. read from the config
+    /// file, can be written as settings for [`Filter::set`]
+    pub fn check(&self) -> crate::Result<()> {
+        self.extensions
+            .iter()
+            .chain(&self.include)
+            .chain(&self.exclude)
+            .try_for_each(|pattern| check_pattern(pattern))
+    }
+}
+
+/// Settings separate list entries by commas and each other by spaces, so
+/// patterns must not contain either
+fn check_pattern(pattern: &str) -> crate::Result<()> {
+    if pattern.contains(|c: char| c == ',' || c.is_whitespace()) {
+        return Err(format!(
+            "invalid filter pattern '{}', use '?' to match a comma or space",
+            pattern
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Parse a size in bytes with an optional `K`, `M` or `G` suffix. `none`
+/// means no limit.
+fn parse_size(s: &str) -> crate::Result<Option<u64>> {
+    let s = s.trim();
+    if s.is_empty() || s == "none" {
+        return Ok(None);
+    }
+    let (digits, unit) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    let size = digits.parse::<u64>()?.checked_mul(unit);
+    Ok(Some(size.ok_or_else(|| format!("size '{}' too large", s))?))
+}
+
+/// Settings in the form accepted by [`Filter::set`]
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let limit = |n: Option<u64>| n.map_or("none".to_string(), |n| n.to_string());
+        write!(
+            f,
+            "ext={} include={} exclude={} hidden={} max-depth={} follow-symlinks={} min-size={} max-size={}",
+            self.extensions.join(","),
+            self.include.join(","),
+            self.exclude.join(","),
+            on_off(self.hidden),
+            limit(self.max_depth.map(|n| n as u64)),
+            on_off(self.follow_symlinks),
+            limit(self.min_size),
+            limit(self.max_size),
+        )
+    }
+}
+
+/// Image to jump to with `GOTO`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Absolute, zero based, position in the collection
+    Index(usize),
+    /// Move relative to the current image, wrapping around
+    Offset(i64),
+    /// Path relative to the image directory
+    Path(PathBuf),
+    /// First image with this file name
+    Name(String),
+}
+
+impl FromStr for Target {
+    type Err = crate::Error;
+
+    /// `12` is an index, `+3` and `-3` are offsets. Anything containing a
+    /// path separator is a relative path, everything else a file name.
+    fn from_str(s: &str) -> crate::Result<Target> {
+        if s.is_empty() {
+            return Err("empty goto target".into());
+        }
+        if let Some(n) = s.strip_prefix('+') {
+            if let Ok(n) = n.parse() {
+                return Ok(Target::Offset(n));
+            }
+        }
+        if s.starts_with('-') {
+            if let Ok(n) = s.parse() {
+                return Ok(Target::Offset(n));
+            }
+        }
+        if let Ok(i) = s.parse() {
+            return Ok(Target::Index(i));
+        }
+        if s.contains(std::path::MAIN_SEPARATOR) {
+            return Ok(Target::Path(PathBuf::from(s)));
+        }
+        Ok(Target::Name(s.to_string()))
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Index(i) => write!(f, "{}", i),
+            Self::Offset(n) => write!(f, "{:+}", n),
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Location of an image in the collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// Zero based index
+    pub index: usize,
+    /// Number of images in the collection
+    pub total: usize,
+    /// Path relative to the image directory
+    pub path: PathBuf,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{} {}", self.index, self.total, self.path.display())
+    }
+}
+
+/// State of the server reported by `INFO`
+#[derive(Debug, Clone, Default, PartialEq, serde_derive::Serialize)]
+pub struct Status {
+    /// Zero based index of the current image
+    pub index: usize,
+    /// Number of images in the collection
+    pub total: usize,
+    /// Path of the current image relative to the image directory
+    pub path: PathBuf,
+    /// Width of the current image in pixels
+    pub width: u32,
+    /// Height of the current image in pixels
+    pub height: u32,
+    /// Size of the current image file in bytes
+    pub file_size: u64,
+    /// Rotation in degrees
+    pub rotation: f64,
+    pub fullscreen: bool,
+    pub scale: ScaleMode,
+    pub zoom: f64,
+    pub sort: SortOrder,
+    pub pageant: bool,
+    /// Milliseconds each image is shown in pageant mode
+    pub pageant_wait: u64,
+    pub pageant_direction: Direction,
+    pub pageant_order: Order,
+    /// Zero based index of the frame shown of an animated image
+    pub frame: usize,
+    /// Number of frames of the current image, 1 unless it is animated
+    pub frames: usize,
+    /// The animation is paused
+    pub paused: bool,
+    /// Seconds since the server started
+    pub uptime: u64,
+    /// Number of connected clients
+    pub clients: usize,
+    /// Files in the image directory which are not supported images
+    pub unsupported: Vec<PathBuf>,
+}
+
+impl Status {
+    /// Name and value of each field, in display order
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let on_off = |on: bool| if on { "on" } else { "off" }.to_string();
+        vec![
+            ("index", self.index.to_string()),
+            ("total", self.total.to_string()),
+            ("path", self.path.display().to_string()),
+            ("width", self.width.to_string()),
+            ("height", self.height.to_string()),
+            ("file_size", self.file_size.to_string()),
+            ("rotation", self.rotation.to_string()),
+            ("fullscreen", on_off(self.fullscreen)),
+            ("scale", self.scale.to_string()),
+            ("zoom", self.zoom.to_string()),
+            ("sort", self.sort.to_string()),
+            ("pageant", on_off(self.pageant)),
+            ("pageant_wait", self.pageant_wait.to_string()),
+            ("pageant_direction", self.pageant_direction.to_string()),
+            ("pageant_order", self.pageant_order.to_string()),
+            ("frame", self.frame.to_string()),
+            ("frames", self.frames.to_string()),
+            ("paused", on_off(self.paused)),
+            ("uptime", self.uptime.to_string()),
+            ("clients", self.clients.to_string()),
+            (
+                "unsupported",
+                self.unsupported
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        ]
+    }
+
+    /// Set a field from its name and the value given by [`Self::fields`].
+    /// Unknown fields are ignored so that newer servers can add some.
+    pub fn set_field(&mut self, name: &str, value: &str) -> crate::Result<()> {
+        let on = |value: &str| match value {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(format!("invalid switch '{}'", value)),
+        };
+        match name {
+            "index" => self.index = value.parse()?,
+            "total" => self.total = value.parse()?,
+            "path" => self.path = value.into(),
+            "width" => self.width = value.parse()?,
+            "height" => self.height = value.parse()?,
+            "file_size" => self.file_size = value.parse()?,
+            "rotation" => self.rotation = value.parse()?,
+            "fullscreen" => self.fullscreen = on(value)?,
+            "scale" => self.scale = value.parse()?,
+            "zoom" => self.zoom = value.parse()?,
+            "sort" => self.sort = value.parse()?,
+            "pageant" => self.pageant = on(value)?,
+            "pageant_wait" => self.pageant_wait = value.parse()?,
+            "pageant_direction" => match value.parse()? {
+                PageantControl::Direction(direction) => self.pageant_direction = direction,
+                _ => return Err(format!("invalid direction '{}'", value).into()),
+            },
+            "pageant_order" => match value.parse()? {
+                PageantControl::Order(order) => self.pageant_order = order,
+                _ => return Err(format!("invalid order '{}'", value).into()),
+            },
+            "frame" => self.frame = value.parse()?,
+            "frames" => self.frames = value.parse()?,
+            "paused" => self.paused = on(value)?,
+            "uptime" => self.uptime = value.parse()?,
+            "clients" => self.clients = value.parse()?,
+            // one path per line
+            "unsupported" => self.unsupported = value.lines().map(PathBuf::from).collect(),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Selection of the collection requested by `LIST` and `SEARCH`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// Glob or substring to match paths against, `None` lists everything
+    pub pattern: Option<String>,
+    /// Number of matching entries to skip
+    pub offset: usize,
+    /// Maximum number of entries returned
+    pub limit: usize,
+}
+
+/// A page of the collection
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Listing {
+    /// Index of the image currently displayed
+    pub current: usize,
+    /// Number of entries matching the query
+    pub total: usize,
+    /// Index and path, relative to the image directory, of each entry
+    pub entries: Vec<(usize, PathBuf)>,
+}
+
+/// Change to the image directory reported by the watcher
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileEvent {
+    /// A file or directory appeared
+    Created(PathBuf),
+    /// A file or directory disappeared
+    Removed(PathBuf),
+    /// A file or directory was moved within the image directory
+    Renamed(PathBuf, PathBuf),
+}
 
 /// Possible commands to execute on the Server, either
 /// Navigating the files under `--path` or executing commands
 /// on the SDL window.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug)]
 pub enum WindowCommand {
     /// Go back to the last image
     Prev,
@@ -16,22 +773,102 @@ pub enum WindowCommand {
     Fullscreen,
     /// Increment angle of rotation
     Rotate,
-    /// Toggle Pageant Mode
-    Pageant,
+    /// Start, stop or tune Pageant Mode
+    Pageant(Vec<PageantControl>),
+    /// Add an image to the collection and display it
+    Open(PathBuf),
+    /// Change how images are scaled to the window
+    Scale(ScaleMode),
+    /// Zoom in or out of the image
+    Zoom(Zoom),
+    /// Move the visible part of a zoomed image
+    Pan(Pan),
+    /// Pause, resume or step through an animated image
+    Animation(AnimationControl),
+    /// Jump to an image, replying with where the cursor landed
+    Goto(Target, oneshot::Sender<Result<Position, String>>),
+    /// Reply with the entries of the collection matching the query
+    List(Query, oneshot::Sender<Listing>),
+    /// Reply with the state of the window and the current image. Server
+    /// wide fields such as uptime are left for the caller to fill in.
+    Info(oneshot::Sender<Status>),
+    /// A file was added to, removed from or renamed in the image directory
+    File(FileEvent),
+    /// Change the order of the collection, replying with the position of
+    /// the current image in it
+    Sort(SortOrder, oneshot::Sender<Position>),
+    /// Change which files are part of the collection and rescan, replying
+    /// with the resulting position. Settings are applied in order as by
+    /// [`Filter::set`], `reset` restores the configured filter.
+    Filter(Vec<String>, oneshot::Sender<Result<Position, String>>),
+    /// Rescan the image directory, replying with the resulting position
+    Reload(oneshot::Sender<Result<Position, String>>),
+    /// Apply a reloaded server config, replying why it was rejected
+    Configure(Arc<Config>, oneshot::Sender<Result<(), String>>),
     /// Exit Window control loop
     Quit,
 }
 
+/// Keys accepted by `SET`
+pub const SET_KEYS: [&str; 9] = [
+    "next",
+    "prev",
+    "fullscreen",
+    "rotate",
+    "pageant",
+    "scale",
+    "zoom",
+    "pan",
+    "animation",
+];
+
+/// Why a `SET` could not be turned into a [`WindowCommand`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetError {
+    /// The key is not one of [`SET_KEYS`]
+    UnknownKey(String),
+    /// The value is not valid for the key
+    InvalidValue { key: String, reason: String },
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownKey(key) => write!(
+                f,
+                "unknown key '{}', expected one of: {}",
+                key,
+                SET_KEYS.join(", ")
+            ),
+            Self::InvalidValue { key, reason } => {
+                write!(f, "invalid value for '{}': {}", key, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
 impl WindowCommand {
-    /// returns WindowCommand for string
-    pub(crate) fn from_str(cmd_name: &str) -> crate::Result<WindowCommand> {
-        let cmd = match cmd_name.to_lowercase().as_str() {
+    /// returns WindowCommand for string and the value it is set to.
+    /// Toggles ignore the value.
+    pub(crate) fn from_str(cmd_name: &str, value: &str) -> Result<WindowCommand, SetError> {
+        let key = cmd_name.to_lowercase();
+        let invalid = |e: crate::Error| SetError::InvalidValue {
+            key: key.clone(),
+            reason: e.to_string(),
+        };
+        let cmd = match key.as_str() {
             "next" => WindowCommand::Next,
             "prev" => WindowCommand::Prev,
             "fullscreen" => WindowCommand::Fullscreen,
             "rotate" => WindowCommand::Rotate,
-            "pageant" => WindowCommand::Pageant,
-            &_ => todo!(),
+            "pageant" => WindowCommand::Pageant(PageantControl::parse_all(value).map_err(invalid)?),
+            "scale" => WindowCommand::Scale(value.parse().map_err(invalid)?),
+            "zoom" => WindowCommand::Zoom(value.parse().map_err(invalid)?),
+            "pan" => WindowCommand::Pan(value.parse().map_err(invalid)?),
+            "animation" => WindowCommand::Animation(value.parse().map_err(invalid)?),
+            _ => return Err(SetError::UnknownKey(cmd_name.to_string())),
         };
 
         Ok(cmd)
@@ -45,8 +882,179 @@ impl fmt::Display for WindowCommand {
             Self::Prev => write!(f, "Previous"),
             Self::Fullscreen => write!(f, "Fullscreen"),
             Self::Rotate => write!(f, "Rotate"),
-            Self::Pageant => write!(f, "Pageant"),
+            Self::Pageant(controls) => write!(f, "Pageant {:?}", controls),
+            Self::Open(path) => write!(f, "Open {}", path.display()),
+            Self::Scale(mode) => write!(f, "Scale {}", mode),
+            Self::Zoom(zoom) => write!(f, "Zoom {}", zoom),
+            Self::Pan(pan) => write!(f, "Pan {}", pan),
+            Self::Animation(control) => write!(f, "Animation {}", control),
+            Self::Goto(target, _) => write!(f, "Goto {}", target),
+            Self::List(query, _) => write!(f, "List {:?}", query),
+            Self::File(event) => write!(f, "File {:?}", event),
+            Self::Reload(_) => write!(f, "Reload"),
+            Self::Configure(..) => write!(f, "Configure"),
+            Self::Sort(order, _) => write!(f, "Sort {}", order),
+            Self::Filter(settings, _) => write!(f, "Filter {}", settings.join(" ")),
+            Self::Info(_) => write!(f, "Info"),
             Self::Quit => write!(f, "Quit"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_from_str() -> crate::Result<()> {
+        assert_eq!(Target::Index(12), "12".parse()?);
+        assert_eq!(Target::Offset(10), "+10".parse()?);
+        assert_eq!(Target::Offset(-5), "-5".parse()?);
+        assert_eq!(Target::Path("a/b.jpg".into()), "a/b.jpg".parse()?);
+        assert_eq!(Target::Name("b.jpg".into()), "b.jpg".parse()?);
+        assert_eq!(Target::Name("-x.jpg".into()), "-x.jpg".parse()?);
+        assert!("".parse::<Target>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_fields_round_trip() -> crate::Result<()> {
+        let info = Status {
+            index: 3,
+            total: 10,
+            path: "a/b.jpg".into(),
+            width: 640,
+            height: 480,
+            file_size: 12345,
+            rotation: 90.0,
+            fullscreen: true,
+            scale: ScaleMode::FitWidth,
+            zoom: 1.25,
+            sort: SortOrder::Shuffle(3),
+            pageant: false,
+            pageant_wait: 1000,
+            pageant_direction: Direction::Reverse,
+            pageant_order: Order::Shuffle,
+            frame: 4,
+            frames: 12,
+            paused: true,
+            uptime: 60,
+            clients: 2,
+            unsupported: vec!["notes.txt".into(), "a/broken.jpg".into()],
+        };
+        let mut parsed = Status::default();
+        for (name, value) in info.fields() {
+            parsed.set_field(name, &value)?;
+        }
+        parsed.set_field("unknown", "x")?;
+        assert_eq!(info, parsed);
+        assert!(parsed.set_field("pageant", "maybe").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_window_command_from_str() {
+        assert!(matches!(
+            WindowCommand::from_str("Next", ""),
+            Ok(WindowCommand::Next)
+        ));
+        let unknown = WindowCommand::from_str("foo", "x").unwrap_err();
+        assert_eq!(unknown, SetError::UnknownKey("foo".into()));
+        assert!(unknown.to_string().contains("next, prev, fullscreen"));
+        assert!(matches!(
+            WindowCommand::from_str("zoom", "lots"),
+            Err(SetError::InvalidValue { key, .. }) if key == "zoom"
+        ));
+        assert!(matches!(
+            WindowCommand::from_str("animation", "pause"),
+            Ok(WindowCommand::Animation(AnimationControl::Pause))
+        ));
+    }
+
+    #[test]
+    fn test_sort_order_from_str() -> crate::Result<()> {
+        assert_eq!(SortOrder::Natural, "natural".parse()?);
+        assert_eq!(SortOrder::Taken, "EXIF".parse()?);
+        assert_eq!(SortOrder::Shuffle(42), "shuffle:42".parse()?);
+        assert!(matches!("shuffle".parse()?, SortOrder::Shuffle(_)));
+        assert!("shuffle:x".parse::<SortOrder>().is_err());
+        let order = SortOrder::Shuffle(7);
+        assert_eq!(order, order.to_string().parse()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() -> crate::Result<()> {
+        let mut filter = Filter::default();
+        assert!(filter.hidden);
+        assert!(!filter.follow_symlinks);
+        filter.set("hidden=off")?;
+        filter.set("ext=JPG,.png")?;
+        filter.set("exclude=**/thumbs/**,*.tmp.jpg")?;
+        filter.set("max-depth=2")?;
+        filter.set("min-size=1k")?;
+        assert!(filter.accepts_file(Path::new("a/b.jpg"), 2048));
+        assert!(filter.accepts_file(Path::new("b.PNG"), 2048));
+        assert!(!filter.accepts_file(Path::new("b.mp4"), 2048));
+        assert!(!filter.accepts_file(Path::new("b.xmp"), 2048));
+        assert!(!filter.accepts_file(Path::new("a/b.jpg"), 100));
+        assert!(!filter.accepts_file(Path::new("a/b/c.jpg"), 2048));
+        assert!(!filter.accepts_file(Path::new("a/thumbs/c.jpg"), 2048));
+        assert!(!filter.accepts_file(Path::new("x.tmp.jpg"), 2048));
+        assert!(!filter.accepts_file(Path::new(".a/b.jpg"), 2048));
+        assert!(filter.accepts_dir(Path::new("a")));
+        assert!(!filter.accepts_dir(Path::new("a/b")));
+        assert!(!filter.accepts_dir(Path::new(".git")));
+
+        let mut parsed = Filter::default();
+        for setting in filter.to_string().split(' ') {
+            parsed.set(setting)?;
+        }
+        assert_eq!(filter, parsed);
+        assert!(filter.set("max-size=lots").is_err());
+        assert!(filter.set("min-size=99999999999999G").is_err());
+        assert_eq!(parse_size("16M")?, Some(16 << 20));
+        assert!(filter.set("colour=red").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_round_trip() -> crate::Result<()> {
+        let mut filter = Filter::default();
+        filter.set("include=my?pics/**, *.jpg")?;
+        filter.set("max-size=none")?;
+        let mut parsed = Filter::default();
+        for setting in filter.to_string().split(' ') {
+            parsed.set(setting)?;
+        }
+        assert_eq!(filter, parsed);
+
+        let err = filter.set("exclude=my pics/**").unwrap_err();
+        assert!(err.to_string().contains("'my pics/**'"), "{}", err);
+        assert_eq!(filter, parsed);
+        let from_config = Filter {
+            include: vec!["a,b".to_string()],
+            ..Filter::default()
+        };
+        assert!(from_config.check().is_err());
+        assert!(parsed.check().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pageant_controls() -> crate::Result<()> {
+        use PageantControl::*;
+        assert_eq!(vec![Toggle], PageantControl::parse_all("")?);
+        assert_eq!(
+            vec![Start, Interval(2000), Direction(super::Direction::Reverse)],
+            PageantControl::parse_all("start 2000ms reverse")?
+        );
+        assert_eq!(
+            vec![Stop, Order(super::Order::Shuffle)],
+            PageantControl::parse_all("OFF shuffle")?
+        );
+        assert!(PageantControl::parse_all("start 0").is_err());
+        assert!(PageantControl::parse_all("faster").is_err());
+        Ok(())
+    }
+}
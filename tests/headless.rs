@@ -0,0 +1,439 @@
+//! Runs the server on a headless display and drives it over TLS with the
+//! client library, checking what the window would have shown.
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, CertificateRevocationList,
+    CertificateRevocationListParams, DnType, IsCa, KeyIdMethod, KeyPair, KeyUsagePurpose,
+    RevokedCertParams, SerialNumber, PKCS_ECDSA_P256_SHA256,
+};
+use std::fs;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use time::OffsetDateTime;
+use viewd::clients::Config as ClientConfig;
+use viewd::cmd::{MAX_UPLOAD_SIZE, UPLOAD_DIR};
+use viewd::sdl_window::{Headless, Recording};
+use viewd::serve::Config;
+use viewd::{control, Client};
+
+/// A 2x1 red PNG
+const PNG: [u8; 71] = [
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0xf4, 0x22, 0x7f,
+    0x8a, 0x00, 0x00, 0x00, 0x0e, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xcf, 0xc0, 0xf0,
+    0x1f, 0x84, 0x01, 0x11, 0xf7, 0x03, 0xfd, 0xe3, 0xc5, 0xf5, 0xef, 0x00, 0x00, 0x00, 0x00, 0x49,
+    0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/// An empty directory for the files of `test`
+fn fixture(test: &str) -> std::io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("viewd-{}-{}", test, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The CA in `dir`, created with a new key unless `dir/ca.key` exists
+fn ca(dir: &Path) -> viewd::Result<Certificate> {
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "viewd test CA");
+    let key = dir.join("ca.key");
+    if key.exists() {
+        params.key_pair = Some(KeyPair::from_pem(&fs::read_to_string(&key)?)?);
+        return Ok(Certificate::from_params(params)?);
+    }
+    let ca = Certificate::from_params(params)?;
+    fs::write(dir.join("ca.pem"), ca.serialize_pem()?)?;
+    fs::write(key, ca.serialize_private_key_pem())?;
+    Ok(ca)
+}
+
+/// Write a CA, a server certificate for `localhost` and a client
+/// certificate for each of `clients` to `dir`
+fn certificates(dir: &Path, clients: &[&str]) -> viewd::Result<()> {
+    let ca = ca(dir)?;
+
+    let names = std::iter::once("localhost").chain(clients.iter().copied());
+    for name in names {
+        let mut params = CertificateParams::new(vec![name.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        let cert = Certificate::from_params(params)?;
+        fs::write(
+            dir.join(format!("{}.pem", name)),
+            cert.serialize_pem_with_signer(&ca)?,
+        )?;
+        fs::write(
+            dir.join(format!("{}.key", name)),
+            cert.serialize_private_key_pem(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write a revocation list to `dir/crl.pem` revoking the certificates of
+/// `clients`
+fn revoke(dir: &Path, clients: &[&str]) -> viewd::Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let mut revoked_certs = vec![];
+    for name in clients {
+        let pem = fs::read(dir.join(format!("{}.pem", name)))?;
+        let (_, pem) = x509_parser::pem::parse_x509_pem(&pem)?;
+        let cert = pem.parse_x509()?;
+        revoked_certs.push(RevokedCertParams {
+            serial_number: SerialNumber::from_slice(cert.raw_serial()),
+            revocation_time: now,
+            reason_code: None,
+            invalidity_date: None,
+        });
+    }
+    let crl = CertificateRevocationList::from_params(CertificateRevocationListParams {
+        this_update: now,
+        next_update: now + time::Duration::days(1),
+        crl_number: SerialNumber::from(1),
+        issuing_distribution_point: None,
+        revoked_certs,
+        alg: &PKCS_ECDSA_P256_SHA256,
+        key_identifier_method: KeyIdMethod::Sha256,
+    })?;
+    fs::write(
+        dir.join("crl.pem"),
+        crl.serialize_pem_with_signer(&ca(dir)?)?,
+    )?;
+    Ok(())
+}
+
+/// A port nothing listens on right now
+fn free_port() -> std::io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Connect as `name`, waiting for the server to come up
+async fn connect(dir: &Path, port: u16, name: &str) -> viewd::Result<Client> {
+    let config = Arc::new(ClientConfig {
+        port,
+        key: dir.join(format!("{}.key", name)),
+        cert: dir.join(format!("{}.pem", name)),
+        ca: dir.join("ca.pem"),
+        host: "localhost".to_string(),
+    });
+    let mut attempts = 50;
+    loop {
+        match Client::connect("127.0.0.1", port, config.clone()).await {
+            Ok(client) => return Ok(client),
+            Err(_) if attempts > 0 => {
+                attempts -= 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Start the server on a headless display, showing the images in
+/// `dir/images` to the clients `laptop`, an admin, and `phone`, a viewer.
+/// Returns the port and what the display shows.
+fn start(dir: &Path) -> viewd::Result<(u16, Arc<Mutex<Recording>>)> {
+    let images = dir.join("images");
+    certificates(dir, &["laptop", "phone"])?;
+    revoke(dir, &[])?;
+
+    let port = free_port()?;
+    let config_path = dir.join("server.toml");
+    fs::write(
+        &config_path,
+        format!(
+            r#"
+path = "{images}"
+ca-file = "{dir}/ca.pem"
+tls-key-file = "{dir}/localhost.key"
+tls-cert-file = "{dir}/localhost.pem"
+crl-file = "{dir}/crl.pem"
+port = {port}
+
+[acl]
+default = "viewer"
+admin = ["laptop"]
+"#,
+            images = images.display(),
+            dir = dir.display(),
+            port = port,
+        ),
+    )?;
+    let config = Config::new(&config_path)?;
+
+    let display = Headless::new((800, 600));
+    let recording = display.recording();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        runtime
+            .block_on(control::run(
+                &[addr],
+                &images,
+                &config_path,
+                config,
+                display,
+            ))
+            .unwrap();
+    });
+    Ok((port, recording))
+}
+
+/// Write a PNG to `dir/images` for each of `names`
+fn images(dir: &Path, names: &[&str]) -> std::io::Result<()> {
+    let images = dir.join("images");
+    fs::create_dir_all(&images)?;
+    for name in names {
+        fs::write(images.join(name), PNG)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_headless_pipeline() -> viewd::Result<()> {
+    let dir = fixture("headless")?;
+    images(&dir, &["a.png", "b.png", "c.png"])?;
+    let (port, recording) = start(&dir)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let status = runtime.block_on(async {
+        let mut client = connect(&dir, port, "laptop").await?;
+        client.set("next", "".into()).await?;
+        client.set("rotate", "".into()).await?;
+        client.set("fullscreen", "".into()).await?;
+        client.goto("c.png").await?;
+
+        // the role comes from the name in the certificate
+        let mut phone = connect(&dir, port, "phone").await?;
+        let denied = phone.set("next", "".into()).await.unwrap_err();
+        assert!(
+            denied.to_string().contains("permission denied"),
+            "{}",
+            denied
+        );
+
+        // replies once every command before it has been shown
+        client.info().await
+    })?;
+    assert_eq!(status.path, PathBuf::from("c.png"));
+    assert_eq!((status.index, status.total), (2, 3));
+    assert_eq!((status.width, status.height), (2, 1));
+    assert!(status.fullscreen);
+
+    let recording = recording.lock().unwrap();
+    let shown: Vec<(&str, f64)> = recording
+        .frames
+        .iter()
+        .map(|frame| {
+            (
+                frame.path.file_name().unwrap().to_str().unwrap(),
+                frame.rotation,
+            )
+        })
+        .collect();
+    assert_eq!(shown.first(), Some(&("a.png", 0.0)));
+    assert!(shown.contains(&("b.png", 0.0)));
+    assert!(shown.contains(&("b.png", 90.0)));
+    // moving to another image keeps the rotation
+    assert_eq!(shown.last(), Some(&("c.png", 90.0)));
+
+    // a 2x1 image fit into 800x600, turned a quarter
+    let last = recording.frames.back().unwrap();
+    assert_eq!((last.width, last.height), (2, 1));
+    assert_eq!((last.rect.width(), last.rect.height()), (600, 300));
+
+    let last = recording.states.back().unwrap();
+    assert_eq!(last.title, "c.png");
+    assert!(last.fullscreen);
+    assert!(recording.states.iter().any(|state| !state.fullscreen));
+    Ok(())
+}
+
+#[test]
+fn test_headless_empty_collection() -> viewd::Result<()> {
+    let dir = fixture("empty")?;
+    images(&dir, &["a.png", "b.png"])?;
+    let (port, recording) = start(&dir)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut client = connect(&dir, port, "laptop").await?;
+        fs::remove_file(dir.join("images/a.png"))?;
+        fs::remove_file(dir.join("images/b.png"))?;
+        // the watcher picks up the removals in the background
+        let mut attempts = 50;
+        while client.info().await?.total > 0 {
+            assert!(attempts > 0, "images were not removed");
+            attempts -= 1;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // the server keeps running without an image to show
+        client.set("next", "".into()).await?;
+        let status = client.info().await?;
+        assert_eq!((status.index, status.total), (0, 0));
+        assert_eq!(status.path, PathBuf::from(""));
+
+        // and shows images added again
+        images(&dir, &["c.png"])?;
+        let mut attempts = 50;
+        while client.info().await?.total == 0 {
+            assert!(attempts > 0, "image was not added");
+            attempts -= 1;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let status = client.info().await?;
+        assert_eq!(status.path, PathBuf::from("c.png"));
+        viewd::Result::Ok(())
+    })?;
+
+    let recording = recording.lock().unwrap();
+    assert!(recording.cleared > 0);
+    let last = recording.frames.back().unwrap();
+    assert_eq!(last.path.file_name().unwrap(), "c.png");
+    Ok(())
+}
+
+#[test]
+fn test_headless_subscribe() -> viewd::Result<()> {
+    let dir = fixture("subscribe")?;
+    images(&dir, &["a.png", "b.png"])?;
+    let (port, _recording) = start(&dir)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut client = connect(&dir, port, "laptop").await?;
+        // the first image has been shown once this replies
+        client.info().await?;
+
+        let phone = connect(&dir, port, "phone").await?;
+        let mut subscriber = phone.subscribe(vec!["display_path".to_string()]).await?;
+        // the current value comes first
+        let message = subscriber.next_message().await?.unwrap();
+        assert_eq!(message.key, "display_path");
+        assert_eq!(&message.value[..], b"a.png");
+
+        client.set("next", "".into()).await?;
+        let message = subscriber.next_message().await?.unwrap();
+        assert_eq!(message.key, "display_path");
+        assert_eq!(&message.value[..], b"b.png");
+        viewd::Result::Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_headless_upload() -> viewd::Result<()> {
+    let dir = fixture("upload")?;
+    images(&dir, &["a.png"])?;
+    let (port, _recording) = start(&dir)?;
+    let uploads = dir.join("images").join(UPLOAD_DIR);
+
+    // larger than a single chunk
+    let local = dir.join("local");
+    fs::create_dir_all(&local)?;
+    let big: Vec<u8> = (0..64 * 1024 + 1).map(|i| i as u8).collect();
+    fs::write(local.join("big.png"), &big)?;
+    fs::write(local.join("a.png"), PNG)?;
+    let huge = fs::File::create(local.join("huge.png"))?;
+    huge.set_len(MAX_UPLOAD_SIZE + 1)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut client = connect(&dir, port, "laptop").await?;
+        client.upload(&local.join("big.png"), false).await?;
+        assert_eq!(fs::read(uploads.join("big.png"))?, big);
+
+        // an existing image is never replaced
+        fs::write(uploads.join("a.png"), b"existing")?;
+        let refused = client
+            .upload(&local.join("a.png"), false)
+            .await
+            .unwrap_err();
+        assert!(
+            refused.to_string().contains("already exists"),
+            "{}",
+            refused
+        );
+        assert_eq!(fs::read(uploads.join("a.png"))?, b"existing");
+
+        let refused = client
+            .upload(&local.join("huge.png"), false)
+            .await
+            .unwrap_err();
+        assert!(refused.to_string().contains("exceeds"), "{}", refused);
+
+        // the connection is still usable after a refused upload
+        client.info().await?;
+        viewd::Result::Ok(())
+    })?;
+
+    // no partial file is left behind
+    let mut names: Vec<_> = fs::read_dir(&uploads)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    names.sort();
+    assert_eq!(names, ["a.png", "big.png"]);
+    Ok(())
+}
+
+#[test]
+fn test_headless_revoked() -> viewd::Result<()> {
+    let dir = fixture("revoked")?;
+    images(&dir, &["a.png"])?;
+    let (port, _recording) = start(&dir)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut laptop = connect(&dir, port, "laptop").await?;
+        let mut phone = connect(&dir, port, "phone").await?;
+        phone.info().await?;
+
+        revoke(&dir, &["phone"])?;
+        laptop.reload_config().await?;
+
+        // the open connection of the revoked client is closed
+        let mut attempts = 50;
+        while phone.info().await.is_ok() {
+            assert!(attempts > 0, "connection was not closed");
+            attempts -= 1;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        laptop.info().await?;
+        viewd::Result::Ok(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_headless_reload_role() -> viewd::Result<()> {
+    let dir = fixture("role")?;
+    images(&dir, &["a.png", "b.png"])?;
+    let (port, _recording) = start(&dir)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut laptop = connect(&dir, port, "laptop").await?;
+        let mut phone = connect(&dir, port, "phone").await?;
+        assert!(phone.set("next", "".into()).await.is_err());
+
+        // the new ACL applies to the open connection
+        let config = dir.join("server.toml");
+        let acl = fs::read_to_string(&config)?
+            .replace(r#"admin = ["laptop"]"#, r#"admin = ["laptop", "phone"]"#);
+        fs::write(&config, acl)?;
+        laptop.reload_config().await?;
+        phone.set("next", "".into()).await?;
+        assert_eq!(phone.info().await?.path, PathBuf::from("b.png"));
+        viewd::Result::Ok(())
+    })?;
+    Ok(())
+}